@@ -5,6 +5,7 @@ use cosmwasm_std::{
 use cw20::{AllowanceResponse, Cw20ReceiveMsg, Expiration};
 
 use crate::error::ContractError;
+use crate::rewards::update_rewards;
 use crate::state::{ALLOWANCES, ALLOWANCES_SPENDER, BALANCES, TOKEN_INFO};
 
 pub fn execute_increase_allowance(
@@ -135,6 +136,13 @@ pub fn execute_transfer_from(
     // deduct allowance before doing anything else have enough allowance
     deduct_allowance(deps.storage, &owner_addr, &info.sender, &env.block, amount)?;
 
+    update_rewards(
+        deps.storage,
+        &deps.querier,
+        &env.contract.address,
+        &[&owner_addr, &rcpt_addr],
+    )?;
+
     BALANCES.update(
         deps.storage,
         &owner_addr,
@@ -171,6 +179,13 @@ pub fn execute_burn_from(
     // deduct allowance before doing anything else have enough allowance
     deduct_allowance(deps.storage, &owner_addr, &info.sender, &env.block, amount)?;
 
+    update_rewards(
+        deps.storage,
+        &deps.querier,
+        &env.contract.address,
+        &[&owner_addr],
+    )?;
+
     // lower balance
     BALANCES.update(
         deps.storage,
@@ -209,6 +224,13 @@ pub fn execute_send_from(
     // deduct allowance before doing anything else have enough allowance
     deduct_allowance(deps.storage, &owner_addr, &info.sender, &env.block, amount)?;
 
+    update_rewards(
+        deps.storage,
+        &deps.querier,
+        &env.contract.address,
+        &[&owner_addr, &rcpt_addr],
+    )?;
+
     // move the tokens to the contract
     BALANCES.update(
         deps.storage,
@@ -283,6 +305,7 @@ mod tests {
             }],
             mint: None,
             marketing: None,
+            reward_denom: None,
         };
         let info = mock_info("creator", &[]);
         let env = mock_env();
@@ -2,7 +2,8 @@
 use cosmwasm_std::entry_point;
 use cosmwasm_std::Order::Ascending;
 use cosmwasm_std::{
-    to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult, Uint128,
+    to_binary, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult,
+    Uint128,
 };
 
 use cw2::{ensure_from_older_version, set_contract_version};
@@ -18,9 +19,10 @@ use crate::allowances::{
 use crate::enumerable::{query_all_accounts, query_owner_allowances, query_spender_allowances};
 use crate::error::ContractError;
 use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+use crate::rewards::{execute_claim_rewards, query_pending_rewards, update_rewards};
 use crate::state::{
-    MinterData, TokenInfo, ALLOWANCES, ALLOWANCES_SPENDER, BALANCES, LOGO, MARKETING_INFO,
-    TOKEN_INFO,
+    MinterData, RewardInfo, TokenInfo, ALLOWANCES, ALLOWANCES_SPENDER, BALANCES, LOGO,
+    MARKETING_INFO, REWARD_INFO, TOKEN_INFO,
 };
 
 // version info for migration info
@@ -127,6 +129,15 @@ pub fn instantiate(
     };
     TOKEN_INFO.save(deps.storage, &data)?;
 
+    if let Some(denom) = msg.reward_denom {
+        let reward_info = RewardInfo {
+            denom,
+            index: Decimal::zero(),
+            accounted: Uint128::zero(),
+        };
+        REWARD_INFO.save(deps.storage, &reward_info)?;
+    }
+
     if let Some(marketing) = msg.marketing {
         let logo = if let Some(logo) = marketing.logo {
             verify_logo(&logo)?;
@@ -232,18 +243,26 @@ pub fn execute(
         ExecuteMsg::UpdateMinter { new_minter } => {
             execute_update_minter(deps, env, info, new_minter)
         }
+        ExecuteMsg::ClaimRewards { recipient } => execute_claim_rewards(deps, env, info, recipient),
     }
 }
 
 pub fn execute_transfer(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     recipient: String,
     amount: Uint128,
 ) -> Result<Response, ContractError> {
     let rcpt_addr = deps.api.addr_validate(&recipient)?;
 
+    update_rewards(
+        deps.storage,
+        &deps.querier,
+        &env.contract.address,
+        &[&info.sender, &rcpt_addr],
+    )?;
+
     BALANCES.update(
         deps.storage,
         &info.sender,
@@ -267,10 +286,17 @@ pub fn execute_transfer(
 
 pub fn execute_burn(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     amount: Uint128,
 ) -> Result<Response, ContractError> {
+    update_rewards(
+        deps.storage,
+        &deps.querier,
+        &env.contract.address,
+        &[&info.sender],
+    )?;
+
     // lower balance
     BALANCES.update(
         deps.storage,
@@ -294,7 +320,7 @@ pub fn execute_burn(
 
 pub fn execute_mint(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     recipient: String,
     amount: Uint128,
@@ -313,6 +339,15 @@ pub fn execute_mint(
         return Err(ContractError::Unauthorized {});
     }
 
+    // settle rewards before the new tokens dilute them
+    let rcpt_addr = deps.api.addr_validate(&recipient)?;
+    update_rewards(
+        deps.storage,
+        &deps.querier,
+        &env.contract.address,
+        &[&rcpt_addr],
+    )?;
+
     // update supply and enforce cap
     config.total_supply += amount;
     if let Some(limit) = config.get_cap() {
@@ -323,7 +358,6 @@ pub fn execute_mint(
     TOKEN_INFO.save(deps.storage, &config)?;
 
     // add amount to recipient balance
-    let rcpt_addr = deps.api.addr_validate(&recipient)?;
     BALANCES.update(
         deps.storage,
         &rcpt_addr,
@@ -339,7 +373,7 @@ pub fn execute_mint(
 
 pub fn execute_send(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     contract: String,
     amount: Uint128,
@@ -347,6 +381,13 @@ pub fn execute_send(
 ) -> Result<Response, ContractError> {
     let rcpt_addr = deps.api.addr_validate(&contract)?;
 
+    update_rewards(
+        deps.storage,
+        &deps.querier,
+        &env.contract.address,
+        &[&info.sender, &rcpt_addr],
+    )?;
+
     // move the tokens to the contract
     BALANCES.update(
         deps.storage,
@@ -504,7 +545,7 @@ pub fn execute_upload_logo(
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Balance { address } => to_binary(&query_balance(deps, address)?),
         QueryMsg::TokenInfo {} => to_binary(&query_token_info(deps)?),
@@ -532,6 +573,9 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
         }
         QueryMsg::MarketingInfo {} => to_binary(&query_marketing_info(deps)?),
         QueryMsg::DownloadLogo {} => to_binary(&query_download_logo(deps)?),
+        QueryMsg::PendingRewards { address } => {
+            to_binary(&query_pending_rewards(deps, env, address)?)
+        }
     }
 }
 
@@ -586,7 +630,7 @@ pub fn query_download_logo(deps: Deps) -> StdResult<DownloadLogoResponse> {
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
     let original_version =
         ensure_from_older_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
@@ -599,6 +643,26 @@ pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, C
             ALLOWANCES_SPENDER.save(deps.storage, (&spender, &owner), &allowance)?;
         }
     }
+
+    // tokens of 0.1.x had neither rewards nor a minter, holders share rewards from now on
+    if let (Some(denom), None) = (msg.reward_denom, REWARD_INFO.may_load(deps.storage)?) {
+        let reward_info = RewardInfo {
+            denom,
+            index: Decimal::zero(),
+            accounted: Uint128::zero(),
+        };
+        REWARD_INFO.save(deps.storage, &reward_info)?;
+    }
+    if let Some(minter) = msg.minter {
+        let mut token_info = TOKEN_INFO.load(deps.storage)?;
+        if token_info.mint.is_none() {
+            token_info.mint = Some(MinterData {
+                minter: deps.api.addr_validate(&minter)?,
+                cap: None,
+            });
+            TOKEN_INFO.save(deps.storage, &token_info)?;
+        }
+    }
     Ok(Response::default())
 }
 
@@ -657,6 +721,7 @@ mod tests {
             }],
             mint: mint.clone(),
             marketing: None,
+            reward_denom: None,
         };
         let info = mock_info("creator", &[]);
         let env = mock_env();
@@ -697,6 +762,7 @@ mod tests {
                 }],
                 mint: None,
                 marketing: None,
+                reward_denom: None,
             };
             let info = mock_info("creator", &[]);
             let env = mock_env();
@@ -737,6 +803,7 @@ mod tests {
                     cap: Some(limit),
                 }),
                 marketing: None,
+                reward_denom: None,
             };
             let info = mock_info("creator", &[]);
             let env = mock_env();
@@ -784,6 +851,7 @@ mod tests {
                     cap: Some(limit),
                 }),
                 marketing: None,
+                reward_denom: None,
             };
             let info = mock_info("creator", &[]);
             let env = mock_env();
@@ -812,6 +880,7 @@ mod tests {
                         marketing: Some("marketing".to_owned()),
                         logo: Some(Logo::Url("url".to_owned())),
                     }),
+                    reward_denom: None,
                 };
 
                 let info = mock_info("creator", &[]);
@@ -851,6 +920,7 @@ mod tests {
                         marketing: Some("m".to_owned()),
                         logo: Some(Logo::Url("url".to_owned())),
                     }),
+                    reward_denom: None,
                 };
 
                 let info = mock_info("creator", &[]);
@@ -1065,6 +1135,7 @@ mod tests {
             ],
             mint: None,
             marketing: None,
+            reward_denom: None,
         };
         let err =
             instantiate(deps.as_mut(), env.clone(), info.clone(), instantiate_msg).unwrap_err();
@@ -1087,6 +1158,7 @@ mod tests {
             ],
             mint: None,
             marketing: None,
+            reward_denom: None,
         };
         let res = instantiate(deps.as_mut(), env, info, instantiate_msg).unwrap();
         assert_eq!(0, res.messages.len());
@@ -1357,6 +1429,7 @@ mod tests {
                         }],
                         mint: None,
                         marketing: None,
+                        reward_denom: None,
                     },
                     &[],
                     "TOKEN",
@@ -1399,7 +1472,11 @@ mod tests {
                 CosmosMsg::Wasm(WasmMsg::Migrate {
                     contract_addr: cw20_addr.to_string(),
                     new_code_id: cw20_id,
-                    msg: to_binary(&MigrateMsg {}).unwrap(),
+                    msg: to_binary(&MigrateMsg {
+                        reward_denom: None,
+                        minter: None,
+                    })
+                    .unwrap(),
                 }),
             )
             .unwrap();
@@ -1458,6 +1535,7 @@ mod tests {
                     marketing: Some("marketing".to_owned()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                reward_denom: None,
             };
 
             let info = mock_info("creator", &[]);
@@ -1511,6 +1589,7 @@ mod tests {
                     marketing: Some("creator".to_owned()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                reward_denom: None,
             };
 
             let info = mock_info("creator", &[]);
@@ -1563,6 +1642,7 @@ mod tests {
                     marketing: Some("creator".to_owned()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                reward_denom: None,
             };
 
             let info = mock_info("creator", &[]);
@@ -1615,6 +1695,7 @@ mod tests {
                     marketing: Some("creator".to_owned()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                reward_denom: None,
             };
 
             let info = mock_info("creator", &[]);
@@ -1667,6 +1748,7 @@ mod tests {
                     marketing: Some("creator".to_owned()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                reward_denom: None,
             };
 
             let info = mock_info("creator", &[]);
@@ -1719,6 +1801,7 @@ mod tests {
                     marketing: Some("creator".to_owned()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                reward_denom: None,
             };
 
             let info = mock_info("creator", &[]);
@@ -1771,6 +1854,7 @@ mod tests {
                     marketing: Some("creator".to_owned()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                reward_denom: None,
             };
 
             let info = mock_info("creator", &[]);
@@ -1826,6 +1910,7 @@ mod tests {
                     marketing: Some("creator".to_owned()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                reward_denom: None,
             };
 
             let info = mock_info("creator", &[]);
@@ -1878,6 +1963,7 @@ mod tests {
                     marketing: Some("creator".to_owned()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                reward_denom: None,
             };
 
             let info = mock_info("creator", &[]);
@@ -1926,6 +2012,7 @@ mod tests {
                     marketing: Some("creator".to_owned()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                reward_denom: None,
             };
 
             let info = mock_info("creator", &[]);
@@ -1976,6 +2063,7 @@ mod tests {
                     marketing: Some("creator".to_owned()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                reward_denom: None,
             };
 
             let info = mock_info("creator", &[]);
@@ -2027,6 +2115,7 @@ mod tests {
                     marketing: Some("creator".to_owned()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                reward_denom: None,
             };
 
             let info = mock_info("creator", &[]);
@@ -2076,6 +2165,7 @@ mod tests {
                     marketing: Some("creator".to_owned()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                reward_denom: None,
             };
 
             let info = mock_info("creator", &[]);
@@ -2132,6 +2222,7 @@ mod tests {
                     marketing: Some("creator".to_owned()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                reward_denom: None,
             };
 
             let info = mock_info("creator", &[]);
@@ -2181,6 +2272,7 @@ mod tests {
                     marketing: Some("creator".to_owned()),
                     logo: Some(Logo::Url("url".to_owned())),
                 }),
+                reward_denom: None,
             };
 
             let info = mock_info("creator", &[]);
@@ -101,6 +101,7 @@ mod tests {
             }],
             mint: None,
             marketing: None,
+            reward_denom: None,
         };
         let info = mock_info("creator", &[]);
         let env = mock_env();
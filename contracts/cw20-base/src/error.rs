@@ -40,4 +40,10 @@ pub enum ContractError {
 
     #[error("Duplicate initial balance addresses")]
     DuplicateInitialBalanceAddresses {},
+
+    #[error("Reward distribution is not enabled for this token")]
+    RewardsDisabled {},
+
+    #[error("No rewards to claim")]
+    NoRewards {},
 }
@@ -9,6 +9,7 @@ Implements:
 - [x] CW20 Base
 - [x] Mintable extension
 - [x] Allowances extension
+- [x] Rewards extension
 
 For more information on this contract, please check out the
 [README](https://github.com/CosmWasm/cw-plus/blob/main/contracts/cw20-base/README.md).
@@ -19,6 +20,7 @@ pub mod contract;
 pub mod enumerable;
 mod error;
 pub mod msg;
+pub mod rewards;
 pub mod state;
 
 pub use crate::error::ContractError;
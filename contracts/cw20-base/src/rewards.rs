@@ -0,0 +1,320 @@
+use cosmwasm_std::{
+    attr, coin, Addr, BankMsg, Decimal, Deps, DepsMut, Env, MessageInfo, QuerierWrapper, Response,
+    StdError, StdResult, Storage, Uint128,
+};
+
+use crate::error::ContractError;
+use crate::msg::PendingRewardsResponse;
+use crate::state::{HolderRewards, RewardInfo, BALANCES, HOLDER_REWARDS, REWARD_INFO, TOKEN_INFO};
+
+/// Folds the reward funds received since the last update into the index.
+/// Funds received while there is no supply wait until there is some.
+fn accrue(
+    storage: &dyn Storage,
+    querier: &QuerierWrapper,
+    contract: &Addr,
+    info: &mut RewardInfo,
+) -> StdResult<()> {
+    let total_supply = TOKEN_INFO.load(storage)?.total_supply;
+    if total_supply.is_zero() {
+        return Ok(());
+    }
+
+    let balance = querier.query_balance(contract, &info.denom)?.amount;
+    let received = balance.saturating_sub(info.accounted);
+    if !received.is_zero() {
+        info.index += Decimal::from_ratio(received, total_supply);
+        info.accounted = balance;
+    }
+    Ok(())
+}
+
+/// Moves what the holder earned since its last checkpoint into its pending rewards.
+fn settle(storage: &dyn Storage, info: &RewardInfo, holder: &Addr) -> StdResult<HolderRewards> {
+    let balance = BALANCES.may_load(storage, holder)?.unwrap_or_default();
+    let mut rewards = HOLDER_REWARDS
+        .may_load(storage, holder)?
+        .unwrap_or_default();
+    rewards.pending += balance * (info.index - rewards.index);
+    rewards.index = info.index;
+    Ok(rewards)
+}
+
+/// Checkpoints the holders, must be called before their balances or the total supply change.
+/// Does nothing for tokens without a reward denom.
+pub fn update_rewards(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    contract: &Addr,
+    holders: &[&Addr],
+) -> StdResult<()> {
+    let mut info = match REWARD_INFO.may_load(storage)? {
+        Some(info) => info,
+        None => return Ok(()),
+    };
+
+    accrue(storage, querier, contract, &mut info)?;
+    REWARD_INFO.save(storage, &info)?;
+    for holder in holders {
+        let rewards = settle(storage, &info, holder)?;
+        HOLDER_REWARDS.save(storage, holder, &rewards)?;
+    }
+    Ok(())
+}
+
+pub fn execute_claim_rewards(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => info.sender.clone(),
+    };
+    let mut reward_info = REWARD_INFO
+        .may_load(deps.storage)?
+        .ok_or(ContractError::RewardsDisabled {})?;
+
+    accrue(
+        deps.storage,
+        &deps.querier,
+        &env.contract.address,
+        &mut reward_info,
+    )?;
+    let mut rewards = settle(deps.storage, &reward_info, &info.sender)?;
+    if rewards.pending.is_zero() {
+        return Err(ContractError::NoRewards {});
+    }
+
+    let amount = rewards.pending;
+    reward_info.accounted = reward_info
+        .accounted
+        .checked_sub(amount)
+        .map_err(StdError::from)?;
+    rewards.pending = Uint128::zero();
+    REWARD_INFO.save(deps.storage, &reward_info)?;
+    HOLDER_REWARDS.save(deps.storage, &info.sender, &rewards)?;
+
+    let res = Response::new()
+        .add_message(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![coin(amount.u128(), &reward_info.denom)],
+        })
+        .add_attributes(vec![
+            attr("action", "claim_rewards"),
+            attr("from", info.sender),
+            attr("to", recipient),
+            attr("amount", amount),
+        ]);
+    Ok(res)
+}
+
+pub fn query_pending_rewards(
+    deps: Deps,
+    env: Env,
+    address: String,
+) -> StdResult<PendingRewardsResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let mut info = REWARD_INFO.load(deps.storage)?;
+    accrue(
+        deps.storage,
+        &deps.querier,
+        &env.contract.address,
+        &mut info,
+    )?;
+    let rewards = settle(deps.storage, &info, &address)?;
+    Ok(PendingRewardsResponse {
+        rewards: coin(rewards.pending.u128(), info.denom),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+        MOCK_CONTRACT_ADDR,
+    };
+    use cosmwasm_std::{coins, CosmosMsg, OwnedDeps, SubMsg};
+    use cw20::Cw20Coin;
+
+    use crate::contract::{execute, instantiate, migrate};
+    use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg};
+
+    const DENOM: &str = "cony";
+
+    fn do_instantiate(deps: DepsMut, addr: &str, amount: u128, reward_denom: Option<&str>) {
+        let instantiate_msg = InstantiateMsg {
+            name: "Auto Gen".to_string(),
+            symbol: "AUTO".to_string(),
+            decimals: 3,
+            initial_balances: vec![Cw20Coin {
+                address: addr.to_string(),
+                amount: Uint128::new(amount),
+            }],
+            mint: Some(cw20::MinterResponse {
+                minter: "minter".to_string(),
+                cap: None,
+            }),
+            marketing: None,
+            reward_denom: reward_denom.map(str::to_string),
+        };
+        let info = mock_info("creator", &[]);
+        instantiate(deps, mock_env(), info, instantiate_msg).unwrap();
+    }
+
+    fn pending(deps: Deps, address: &str) -> u128 {
+        query_pending_rewards(deps, mock_env(), address.to_string())
+            .unwrap()
+            .rewards
+            .amount
+            .u128()
+    }
+
+    // the mock querier does not see bank messages, so rewards are paid in and out by hand
+    fn set_reward_balance(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, amount: u128) {
+        deps.querier
+            .update_balance(MOCK_CONTRACT_ADDR, coins(amount, DENOM));
+    }
+
+    fn claim(deps: DepsMut, sender: &str) -> Result<Response, ContractError> {
+        let info = mock_info(sender, &[]);
+        let msg = ExecuteMsg::ClaimRewards { recipient: None };
+        execute(deps, mock_env(), info, msg)
+    }
+
+    #[test]
+    fn rewards_follow_balances() {
+        let mut deps = mock_dependencies();
+        let addr1 = "addr0001";
+        let addr2 = "addr0002";
+        do_instantiate(deps.as_mut(), addr1, 1000, Some(DENOM));
+
+        // everything received so far belongs to the only holder
+        set_reward_balance(&mut deps, 100);
+        assert_eq!(pending(deps.as_ref(), addr1), 100);
+
+        // a transfer does not move rewards earned before it
+        let info = mock_info(addr1, &[]);
+        let msg = ExecuteMsg::Transfer {
+            recipient: addr2.to_string(),
+            amount: Uint128::new(250),
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(pending(deps.as_ref(), addr1), 100);
+        assert_eq!(pending(deps.as_ref(), addr2), 0);
+
+        // later rewards are shared pro rata
+        set_reward_balance(&mut deps, 500);
+        assert_eq!(pending(deps.as_ref(), addr1), 400);
+        assert_eq!(pending(deps.as_ref(), addr2), 100);
+
+        let res = claim(deps.as_mut(), addr1).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: addr1.to_string(),
+                amount: coins(400, DENOM),
+            }))]
+        );
+        set_reward_balance(&mut deps, 100);
+        assert_eq!(pending(deps.as_ref(), addr1), 0);
+        assert_eq!(pending(deps.as_ref(), addr2), 100);
+
+        // nothing left for the first holder
+        let err = claim(deps.as_mut(), addr1).unwrap_err();
+        assert_eq!(err, ContractError::NoRewards {});
+    }
+
+    #[test]
+    fn minted_and_burned_tokens_settle_first() {
+        let mut deps = mock_dependencies();
+        let addr1 = "addr0001";
+        let addr2 = "addr0002";
+        do_instantiate(deps.as_mut(), addr1, 1000, Some(DENOM));
+        set_reward_balance(&mut deps, 100);
+
+        // newly minted tokens do not share rewards received before
+        let info = mock_info("minter", &[]);
+        let msg = ExecuteMsg::Mint {
+            recipient: addr2.to_string(),
+            amount: Uint128::new(1000),
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(pending(deps.as_ref(), addr1), 100);
+        assert_eq!(pending(deps.as_ref(), addr2), 0);
+
+        // burned tokens keep what they earned
+        let info = mock_info(addr1, &[]);
+        let msg = ExecuteMsg::Burn {
+            amount: Uint128::new(1000),
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        set_reward_balance(&mut deps, 300);
+        assert_eq!(pending(deps.as_ref(), addr1), 100);
+        assert_eq!(pending(deps.as_ref(), addr2), 200);
+    }
+
+    #[test]
+    fn claim_to_recipient() {
+        let mut deps = mock_dependencies();
+        let addr1 = "addr0001";
+        do_instantiate(deps.as_mut(), addr1, 1000, Some(DENOM));
+        set_reward_balance(&mut deps, 100);
+
+        let info = mock_info(addr1, &[]);
+        let msg = ExecuteMsg::ClaimRewards {
+            recipient: Some("recipient".to_string()),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "recipient".to_string(),
+                amount: coins(100, DENOM),
+            }))]
+        );
+        set_reward_balance(&mut deps, 0);
+        assert_eq!(pending(deps.as_ref(), addr1), 0);
+    }
+
+    #[test]
+    fn rewards_disabled_without_denom() {
+        let mut deps = mock_dependencies();
+        let addr1 = "addr0001";
+        do_instantiate(deps.as_mut(), addr1, 1000, None);
+        set_reward_balance(&mut deps, 100);
+
+        let err = claim(deps.as_mut(), addr1).unwrap_err();
+        assert_eq!(err, ContractError::RewardsDisabled {});
+    }
+
+    #[test]
+    fn migrate_enables_rewards() {
+        let mut deps = mock_dependencies();
+        let addr1 = "addr0001";
+        do_instantiate(deps.as_mut(), addr1, 1000, None);
+        // tokens of 0.1.x were instantiated without a minter
+        TOKEN_INFO
+            .update(deps.as_mut().storage, |mut info| -> StdResult<_> {
+                info.mint = None;
+                Ok(info)
+            })
+            .unwrap();
+        cw2::set_contract_version(deps.as_mut().storage, "crates.io:cw20-base", "0.1.0").unwrap();
+
+        let msg = MigrateMsg {
+            reward_denom: Some(DENOM.to_string()),
+            minter: Some("portal".to_string()),
+        };
+        migrate(deps.as_mut(), mock_env(), msg).unwrap();
+        let minter = TOKEN_INFO.load(&deps.storage).unwrap().mint.unwrap().minter;
+        assert_eq!(minter, Addr::unchecked("portal"));
+
+        set_reward_balance(&mut deps, 100);
+        assert_eq!(pending(deps.as_ref(), addr1), 100);
+        claim(deps.as_mut(), addr1).unwrap();
+    }
+}
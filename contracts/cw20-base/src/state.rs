@@ -1,5 +1,5 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Uint128};
+use cosmwasm_std::{Addr, Decimal, Uint128};
 use cw_storage_plus::{Item, Map};
 
 use cw20::{AllowanceResponse, Logo, MarketingInfoResponse};
@@ -26,6 +26,23 @@ impl TokenInfo {
     }
 }
 
+#[cw_serde]
+pub struct RewardInfo {
+    pub denom: String,
+    /// rewards per token accumulated since instantiation
+    pub index: Decimal,
+    /// reward funds already folded into the index and not claimed yet
+    pub accounted: Uint128,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct HolderRewards {
+    /// reward index at the last checkpoint of the holder
+    pub index: Decimal,
+    pub pending: Uint128,
+}
+
 pub const TOKEN_INFO: Item<TokenInfo> = Item::new("token_info");
 pub const MARKETING_INFO: Item<MarketingInfoResponse> = Item::new("marketing_info");
 pub const LOGO: Item<Logo> = Item::new("logo");
@@ -34,3 +51,5 @@ pub const ALLOWANCES: Map<(&Addr, &Addr), AllowanceResponse> = Map::new("allowan
 // TODO: After https://github.com/CosmWasm/cw-plus/issues/670 is implemented, replace this with a `MultiIndex` over `ALLOWANCES`
 pub const ALLOWANCES_SPENDER: Map<(&Addr, &Addr), AllowanceResponse> =
     Map::new("allowance_spender");
+pub const REWARD_INFO: Item<RewardInfo> = Item::new("reward_info");
+pub const HOLDER_REWARDS: Map<&Addr, HolderRewards> = Map::new("holder_rewards");
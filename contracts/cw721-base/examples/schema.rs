@@ -1,12 +1,12 @@
 use cosmwasm_schema::write_api;
 use cosmwasm_std::Empty;
 
-use cw721_base::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use cw721_base::{ExecuteMsg, Extension, ExtensionMsg, InstantiateMsg, QueryMsg};
 
 fn main() {
     write_api! {
         instantiate: InstantiateMsg,
-        execute: ExecuteMsg<Empty, Empty>,
+        execute: ExecuteMsg<Extension, ExtensionMsg>,
         query: QueryMsg<Empty>,
     }
 }
@@ -23,10 +23,46 @@ pub use crate::state::Cw721Contract;
 // `ContractError::Ownership`.
 pub use cw_ownable::{Action, Ownership, OwnershipError};
 
-use cosmwasm_std::Empty;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Coin, CustomMsg, Empty, Timestamp};
 
-// This is a simple type to let us handle empty extensions
-pub type Extension = Option<Empty>;
+/// Stake a liquid staking record stands for, set when the portal mints it.
+/// Records minted before the extension existed read as the default.
+#[cw_serde]
+#[derive(Default)]
+#[serde(default)]
+pub struct Metadata {
+    /// Name of the position token
+    pub name: String,
+    pub description: Option<String>,
+    /// Validator the position was created with
+    pub validator: String,
+    /// Native tokens delegated when the position was created
+    pub initial_amount: Coin,
+    /// Native tokens delegated after the last delegation or undelegation of the position
+    pub delegated: Coin,
+    /// LSCT contract of the position
+    pub token_address: String,
+    /// Delegator contract of the position
+    pub delegator_address: String,
+    pub created_height: u64,
+    pub created_time: Timestamp,
+}
+
+pub type Extension = Option<Metadata>;
+
+/// Messages of the minter, which keeps the records current
+#[cw_serde]
+pub enum ExtensionMsg {
+    /// Replace the token uri and metadata of a record, only callable by the minter
+    UpdateMetadata {
+        token_id: String,
+        token_uri: Option<String>,
+        extension: Extension,
+    },
+}
+
+impl CustomMsg for ExtensionMsg {}
 
 // Version info for migration
 pub const CONTRACT_NAME: &str = "crates.io:cw721-base";
@@ -43,7 +79,7 @@ pub mod entry {
 
     #[cfg(not(feature = "library"))]
     use cosmwasm_std::entry_point;
-    use cosmwasm_std::{Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+    use cosmwasm_std::{Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult};
 
     // This makes a conscious choice on the various generics used by the contract
     #[cfg_attr(not(feature = "library"), entry_point)]
@@ -55,7 +91,7 @@ pub mod entry {
     ) -> StdResult<Response> {
         cw2::set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
-        let tract = Cw721Contract::<Extension, Empty, Empty, Empty>::default();
+        let tract = Cw721Contract::<Extension, Empty, ExtensionMsg, Empty>::default();
         tract.instantiate(deps, env, info, msg)
     }
 
@@ -64,20 +100,57 @@ pub mod entry {
         deps: DepsMut,
         env: Env,
         info: MessageInfo,
-        msg: ExecuteMsg<Extension, Empty>,
+        msg: ExecuteMsg<Extension, ExtensionMsg>,
     ) -> Result<Response, ContractError> {
-        let tract = Cw721Contract::<Extension, Empty, Empty, Empty>::default();
-        tract.execute(deps, env, info, msg)
+        let tract = Cw721Contract::<Extension, Empty, ExtensionMsg, Empty>::default();
+        match msg {
+            ExecuteMsg::Extension {
+                msg:
+                    ExtensionMsg::UpdateMetadata {
+                        token_id,
+                        token_uri,
+                        extension,
+                    },
+            } => {
+                cw_ownable::assert_owner(deps.storage, &info.sender)?;
+                tract
+                    .tokens
+                    .update(deps.storage, &token_id, |token| match token {
+                        Some(mut token) => {
+                            token.token_uri = token_uri;
+                            token.extension = extension;
+                            Ok(token)
+                        }
+                        None => Err(ContractError::Std(StdError::not_found("token"))),
+                    })?;
+                Ok(Response::new()
+                    .add_attribute("action", "update_metadata")
+                    .add_attribute("token_id", token_id))
+            }
+            msg => tract.execute(deps, env, info, msg),
+        }
     }
 
     #[cfg_attr(not(feature = "library"), entry_point)]
     pub fn query(deps: Deps, env: Env, msg: QueryMsg<Empty>) -> StdResult<Binary> {
-        let tract = Cw721Contract::<Extension, Empty, Empty, Empty>::default();
+        let tract = Cw721Contract::<Extension, Empty, ExtensionMsg, Empty>::default();
         tract.query(deps, env, msg)
     }
 
     #[cfg_attr(not(feature = "library"), entry_point)]
     pub fn migrate(deps: DepsMut, _env: Env, _msg: Empty) -> Result<Response, ContractError> {
+        // records of 0.1.x keep their empty extension, which reads as the default metadata
+        // until the minter rewrites it
+        let stored = cw2::get_contract_version(deps.storage)?;
+        if stored.contract == CONTRACT_NAME && stored.version != EXPECTED_FROM_VERSION {
+            let original_version =
+                cw2::ensure_from_older_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+            return Ok(Response::new()
+                .add_attribute("action", "migrate")
+                .add_attribute("from_version", original_version.to_string())
+                .add_attribute("to_version", CONTRACT_VERSION));
+        }
+
         // make sure the correct contract is being upgraded, and it's being
         // upgraded from the correct version.
         cw2::assert_contract_version(deps.as_ref().storage, CONTRACT_NAME, EXPECTED_FROM_VERSION)?;
@@ -86,13 +159,14 @@ pub mod entry {
         cw2::set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
         // perform the upgrade
-        upgrades::v0_17::migrate::<Extension, Empty, Empty, Empty>(deps)
+        upgrades::v0_17::migrate::<Extension, Empty, ExtensionMsg, Empty>(deps)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::{from_binary, Binary, StdError};
     use cw2::ContractVersion;
 
     use super::*;
@@ -123,4 +197,84 @@ mod tests {
             },
         );
     }
+
+    /// Records minted with an empty extension still load.
+    #[test]
+    fn empty_extension_reads_as_default() {
+        let extension: Extension = from_binary(&Binary::from(b"{}")).unwrap();
+        assert_eq!(extension, Some(Metadata::default()));
+    }
+
+    /// Only the minter updates the metadata of a record.
+    #[test]
+    fn minter_updates_metadata() {
+        let mut deps = mock_dependencies();
+        entry::instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("larry", &[]),
+            InstantiateMsg {
+                name: "".into(),
+                symbol: "".into(),
+                minter: "larry".into(),
+            },
+        )
+        .unwrap();
+        let mint = ExecuteMsg::Mint {
+            token_id: "1".into(),
+            owner: "owner".into(),
+            token_uri: None,
+            extension: Some(Metadata::default()),
+        };
+        entry::execute(deps.as_mut(), mock_env(), mock_info("larry", &[]), mint).unwrap();
+
+        let metadata = Metadata {
+            name: "record".into(),
+            ..Metadata::default()
+        };
+        let update = |token_id: &str| ExecuteMsg::Extension {
+            msg: ExtensionMsg::UpdateMetadata {
+                token_id: token_id.into(),
+                token_uri: Some("uri".into()),
+                extension: Some(metadata.clone()),
+            },
+        };
+        let err = entry::execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            update("1"),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Ownership(OwnershipError::NotOwner));
+        let err = entry::execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("larry", &[]),
+            update("2"),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Std(StdError::NotFound { .. })));
+
+        entry::execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("larry", &[]),
+            update("1"),
+        )
+        .unwrap();
+        let info: cw721::NftInfoResponse<Extension> = from_binary(
+            &entry::query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::NftInfo {
+                    token_id: "1".into(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(info.token_uri, Some("uri".to_string()));
+        assert_eq!(info.extension, Some(metadata));
+    }
 }
@@ -1,8 +1,10 @@
-use cosmwasm_std::{to_binary, Addr, Empty, QuerierWrapper, WasmMsg};
+use cosmwasm_std::{
+    to_binary, Addr, Deps, DepsMut, Empty, Env, MessageInfo, QuerierWrapper, WasmMsg,
+};
 use cw721::OwnerOfResponse;
 use cw_multi_test::{App, Contract, ContractWrapper, Executor};
 
-use crate::MinterResponse;
+use crate::{Extension, Metadata, MinterResponse};
 
 fn cw721_base_contract() -> Box<dyn Contract<Empty>> {
     let contract = ContractWrapper::new(
@@ -24,6 +26,25 @@ fn cw721_base_016_contract() -> Box<dyn Contract<Empty>> {
     Box::new(contract)
 }
 
+/// The contract as 0.1.x deployed it, minting records without metadata
+fn cw721_base_01_contract() -> Box<dyn Contract<Empty>> {
+    type Legacy<'a> = crate::Cw721Contract<'a, Option<Empty>, Empty, Empty, Empty>;
+    let contract = ContractWrapper::new(
+        |deps: DepsMut,
+         env: Env,
+         info: MessageInfo,
+         msg: crate::ExecuteMsg<Option<Empty>, Empty>| {
+            Legacy::default().execute(deps, env, info, msg)
+        },
+        |deps: DepsMut, env: Env, info: MessageInfo, msg: crate::InstantiateMsg| {
+            cw2::set_contract_version(deps.storage, crate::CONTRACT_NAME, "0.1.0")?;
+            Legacy::default().instantiate(deps, env, info, msg)
+        },
+        |deps: Deps, env: Env, msg: crate::QueryMsg<Empty>| Legacy::default().query(deps, env, msg),
+    );
+    Box::new(contract)
+}
+
 fn query_owner(querier: QuerierWrapper, cw721: &Addr, token_id: String) -> Addr {
     let resp: OwnerOfResponse = querier
         .query_wasm_smart(
@@ -133,3 +154,85 @@ fn test_016_017_migration() {
         .unwrap();
     assert_eq!(m.minter, admin().to_string());
 }
+
+/// Instantiates the 0.1.x version of this contract and tests that its records
+/// read as the default metadata after migration, until the minter rewrites them.
+#[test]
+fn test_01_02_migration() {
+    let mut app = App::default();
+    let admin = || Addr::unchecked("admin");
+
+    let code_id_01 = app.store_code(cw721_base_01_contract());
+    let code_id_02 = app.store_code(cw721_base_contract());
+
+    let cw721 = app
+        .instantiate_contract(
+            code_id_01,
+            admin(),
+            &crate::InstantiateMsg {
+                name: "collection".to_string(),
+                symbol: "symbol".to_string(),
+                minter: admin().into_string(),
+            },
+            &[],
+            "cw721-base",
+            Some(admin().into_string()),
+        )
+        .unwrap();
+    app.execute_contract(
+        admin(),
+        cw721.clone(),
+        &crate::ExecuteMsg::<Option<Empty>, Empty>::Mint {
+            token_id: "1".to_string(),
+            owner: admin().into_string(),
+            token_uri: None,
+            extension: Some(Empty {}),
+        },
+        &[],
+    )
+    .unwrap();
+
+    app.execute(
+        admin(),
+        WasmMsg::Migrate {
+            contract_addr: cw721.to_string(),
+            new_code_id: code_id_02,
+            msg: to_binary(&Empty::default()).unwrap(),
+        }
+        .into(),
+    )
+    .unwrap();
+    let version = cw2::CONTRACT.query(&app.wrap(), cw721.clone()).unwrap();
+    assert_eq!(version.version, crate::CONTRACT_VERSION);
+
+    let nft_info = |app: &App| -> cw721::NftInfoResponse<Extension> {
+        app.wrap()
+            .query_wasm_smart(
+                &cw721,
+                &crate::QueryMsg::<Empty>::NftInfo {
+                    token_id: "1".to_string(),
+                },
+            )
+            .unwrap()
+    };
+    assert_eq!(nft_info(&app).extension, Some(Metadata::default()));
+
+    let metadata = Metadata {
+        name: "record".to_string(),
+        ..Metadata::default()
+    };
+    app.execute_contract(
+        admin(),
+        cw721.clone(),
+        &crate::ExecuteMsg::<Extension, crate::ExtensionMsg>::Extension {
+            msg: crate::ExtensionMsg::UpdateMetadata {
+                token_id: "1".to_string(),
+                token_uri: None,
+                extension: Some(metadata.clone()),
+            },
+        },
+        &[],
+    )
+    .unwrap();
+    assert_eq!(nft_info(&app).extension, Some(metadata));
+}
@@ -1,13 +1,18 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    BankMsg, Binary, Coin, Deps, DepsMut, DistributionMsg, Env, MessageInfo, Reply, Response,
-    StakingMsg, StdResult, Uint128,
+    to_json_binary, BankMsg, Binary, Coin, Deps, DepsMut, DistributionMsg, Env, MessageInfo, Reply,
+    Response, StakingMsg, StdResult, Timestamp, Uint128,
 };
-use cw2::set_contract_version;
+use cw2::{ensure_from_older_version, set_contract_version};
 
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+use crate::msg::{
+    ConfigResponse, DelegationResponse, ExecuteMsg, Fee, IdleBalanceResponse, InstantiateMsg,
+    MigrateMsg, PendingRewardsResponse, QueryMsg, UnbondingInfo, UnbondingsResponse,
+};
+use crate::querier::{query_unbonding_entries, UnbondingEntry};
+use crate::state::{Unbonding, RELEASED, UNBONDINGS, VALIDATOR};
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:delegator";
@@ -27,10 +32,19 @@ pub fn instantiate(
     // TODO: validate msg
 
     cw_ownable::initialize_owner(deps.storage, deps.api, Some(&info.sender.to_string()))?;
+    VALIDATOR.save(deps.storage, &msg.validator)?;
 
+    let bonded_denom = deps.querier.query_bonded_denom()?;
+    let coin = info
+        .funds
+        .iter()
+        .find(|coin| coin.denom == bonded_denom)
+        .ok_or(ContractError::NoFunds {
+            denom: bonded_denom,
+        })?;
     let delegate_msg = StakingMsg::Delegate {
         validator: msg.validator,
-        amount: info.funds[0].clone(),
+        amount: coin.clone(),
     };
 
     Ok(Response::new()
@@ -40,33 +54,57 @@ pub fn instantiate(
 }
 
 /// Handling contract migration
-/// To make a contract migratable, you need
-/// - this entry_point implemented
-/// - only contract admin can migrate, so admin has to be set at contract initiation time
-/// Handling contract execution
+/// Only the contract admin, the portal, can migrate. Downgrades and other contracts are rejected.
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(_deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
-    match msg {
-        // Find matched incoming message variant and execute them with your custom logic.
-        //
-        // With `Response` type, it is possible to dispatch message to invoke external logic.
-        // See: https://github.com/CosmWasm/cosmwasm/blob/main/SEMANTICS.md#dispatching-messages
+pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let original_version =
+        ensure_from_older_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    if original_version < semver::Version::new(0, 2, 0)
+        && VALIDATOR.may_load(deps.storage)?.is_none()
+    {
+        // the validator was not stored before 0.2.0, recover it from the delegation
+        let delegations = deps.querier.query_all_delegations(env.contract.address)?;
+        if let Some(delegation) = delegations.first() {
+            VALIDATOR.save(deps.storage, &delegation.validator)?;
+        }
     }
+
+    Ok(Response::new()
+        .add_attribute("method", "migrate")
+        .add_attribute("from_version", original_version.to_string())
+        .add_attribute("to_version", CONTRACT_VERSION))
 }
 
 /// Handling contract execution
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
-    cw_ownable::assert_owner(deps.storage, &info.sender).unwrap();
+    cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+    // the chain only shows a slash on the entries of pending unbondings, so every call records
+    // it, but a delegation, whose funds already count in the balance
+    if !matches!(msg, ExecuteMsg::Delegate {}) {
+        update_unbondings(deps.branch(), &env)?;
+    }
 
     match msg {
-        ExecuteMsg::WithdrawReward { recipient } => execute_withdraw_reward(deps, env, recipient),
-        ExecuteMsg::Undelegate { amount } => execute_undelegate(deps, env, amount),
+        ExecuteMsg::WithdrawReward { recipient, fee } => {
+            execute_withdraw_reward(deps, env, recipient, fee)
+        }
+        ExecuteMsg::Undelegate {
+            id,
+            amount,
+            completion_time,
+        } => execute_undelegate(deps, env, id, amount, completion_time),
+        ExecuteMsg::Compound { recipient, fee } => execute_compound(deps, env, recipient, fee),
+        ExecuteMsg::Delegate {} => execute_delegate(deps, info),
+        ExecuteMsg::Redelegate { dst_validator } => execute_redelegate(deps, env, dst_validator),
+        ExecuteMsg::Claim { recipient, ids } => execute_claim(deps, recipient, ids),
     }
 }
 
@@ -74,55 +112,420 @@ fn execute_withdraw_reward(
     deps: DepsMut,
     env: Env,
     recipient: String,
+    fee: Option<Fee>,
+) -> Result<Response, ContractError> {
+    let validator = VALIDATOR.load(deps.storage)?;
+
+    // rewards land here first, so the fee can be taken before paying out
+    let rewards = deps
+        .querier
+        .query_delegation(env.contract.address, validator.clone())?
+        .ok_or(ContractError::NoDelegation {})?
+        .accumulated_rewards;
+    let (fees, payout) = split_fee(rewards, fee.as_ref());
+    let fee_amount = join_coins(&fees);
+
+    let mut res =
+        Response::new().add_message(DistributionMsg::WithdrawDelegatorReward { validator });
+    if let (Some(fee), false) = (fee, fees.is_empty()) {
+        res = res.add_message(BankMsg::Send {
+            to_address: fee.recipient,
+            amount: fees,
+        });
+    }
+    if !payout.is_empty() {
+        res = res.add_message(BankMsg::Send {
+            to_address: recipient,
+            amount: payout,
+        });
+    }
+
+    Ok(res
+        .add_attribute("method", "execute")
+        .add_attribute("action", "withdraw_reward")
+        .add_attribute("fee_amount", fee_amount))
+}
+
+/// Splits `rewards` into the fee and the rest, leaving out zero coins
+fn split_fee(rewards: Vec<Coin>, fee: Option<&Fee>) -> (Vec<Coin>, Vec<Coin>) {
+    let mut fees = vec![];
+    let mut rest = vec![];
+    for coin in rewards.into_iter().filter(|coin| !coin.amount.is_zero()) {
+        let fee_amount = fee_share(coin.amount, fee);
+        if !fee_amount.is_zero() {
+            fees.push(Coin {
+                denom: coin.denom.clone(),
+                amount: fee_amount,
+            });
+        }
+        if coin.amount > fee_amount {
+            rest.push(Coin {
+                denom: coin.denom,
+                amount: coin.amount - fee_amount,
+            });
+        }
+    }
+    (fees, rest)
+}
+
+/// Part of `amount` owed as fee, rounded down
+fn fee_share(amount: Uint128, fee: Option<&Fee>) -> Uint128 {
+    fee.map_or(Uint128::zero(), |fee| {
+        amount.multiply_ratio(fee.bps, 10_000u128)
+    })
+}
+
+/// Lists the coins the way the `fee_amount` attribute reports them
+fn join_coins(coins: &[Coin]) -> String {
+    coins
+        .iter()
+        .map(Coin::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn execute_undelegate(
+    deps: DepsMut,
+    env: Env,
+    id: u64,
+    amount: Uint128,
+    completion_time: Timestamp,
 ) -> Result<Response, ContractError> {
-    // get validator
-    let delegations = deps
+    let validator = VALIDATOR.load(deps.storage)?;
+    let delegated = deps
         .querier
-        .query_all_delegations(env.contract.clone().address)?;
+        .query_delegation(env.contract.address, validator.clone())?
+        .ok_or(ContractError::NoDelegation {})?
+        .amount;
+    if amount > delegated.amount {
+        return Err(ContractError::InsufficientDelegation {
+            requested: amount,
+            available: delegated.amount,
+        });
+    }
+
+    let mut unbondings = UNBONDINGS.may_load(deps.storage)?.unwrap_or_default();
+    if unbondings.iter().any(|unbonding| unbonding.id == id) {
+        return Err(ContractError::DuplicateUnbonding { id });
+    }
+    unbondings.push(Unbonding {
+        id,
+        validator: validator.clone(),
+        creation_height: env.block.height,
+        initial_amount: amount,
+        amount,
+        start_time: env.block.time,
+        completion_time,
+        released: false,
+    });
+    UNBONDINGS.save(deps.storage, &unbondings)?;
 
     Ok(Response::new()
-        .add_message(DistributionMsg::SetWithdrawAddress { address: recipient })
-        .add_message(DistributionMsg::WithdrawDelegatorReward {
-            validator: delegations[0].validator.clone(),
-        })
-        .add_message(DistributionMsg::SetWithdrawAddress {
-            address: env.contract.clone().address.to_string(),
+        .add_message(StakingMsg::Undelegate {
+            validator,
+            amount: Coin {
+                denom: delegated.denom,
+                amount,
+            },
         })
         .add_attribute("method", "execute")
-        .add_attribute("action", "withdraw_reward"))
+        .add_attribute("action", "undelegate"))
 }
 
-fn execute_undelegate(deps: DepsMut, env: Env, amount: Uint128) -> Result<Response, ContractError> {
-    let delegations = deps
+fn execute_compound(
+    deps: DepsMut,
+    env: Env,
+    recipient: String,
+    fee: Option<Fee>,
+) -> Result<Response, ContractError> {
+    let validator = VALIDATOR.load(deps.storage)?;
+
+    // only the rewards are restaked, unbonded coins waiting to be claimed stay idle
+    let bonded_denom = deps.querier.query_bonded_denom()?;
+    let rewards = deps
         .querier
-        .query_all_delegations(env.contract.clone().address)?;
+        .query_delegation(env.contract.address, validator.clone())?
+        .ok_or(ContractError::NoDelegation {})?
+        .accumulated_rewards;
+    let (fees, rest) = split_fee(rewards, fee.as_ref());
+    let fee_amount = join_coins(&fees);
 
-    let coin = Coin {
-        denom: deps.querier.query_bonded_denom()?,
-        amount: amount,
-    };
+    // every reward denom is withdrawn, those that cannot be delegated are paid out
+    let (restaked, payout): (Vec<Coin>, Vec<Coin>) = rest
+        .into_iter()
+        .partition(|coin| coin.denom == bonded_denom);
+    let restaked: Uint128 = restaked.into_iter().map(|coin| coin.amount).sum();
+
+    let mut res = Response::new().add_message(DistributionMsg::WithdrawDelegatorReward {
+        validator: validator.clone(),
+    });
+    if let (Some(fee), false) = (fee, fees.is_empty()) {
+        res = res.add_message(BankMsg::Send {
+            to_address: fee.recipient,
+            amount: fees,
+        });
+    }
+    if !restaked.is_zero() {
+        res = res.add_message(StakingMsg::Delegate {
+            validator,
+            amount: Coin {
+                denom: bonded_denom,
+                amount: restaked,
+            },
+        });
+    }
+    if !payout.is_empty() {
+        res = res.add_message(BankMsg::Send {
+            to_address: recipient,
+            amount: payout,
+        });
+    }
+
+    Ok(res
+        .add_attribute("method", "execute")
+        .add_attribute("action", "compound")
+        .add_attribute("amount", restaked)
+        .add_attribute("fee_amount", fee_amount))
+}
+
+fn execute_delegate(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let validator = VALIDATOR.load(deps.storage)?;
+    let bonded_denom = deps.querier.query_bonded_denom()?;
+    let coin = info
+        .funds
+        .into_iter()
+        .find(|coin| coin.denom == bonded_denom)
+        .ok_or(ContractError::NoFunds {
+            denom: bonded_denom,
+        })?;
 
     Ok(Response::new()
-        .add_message(StakingMsg::Undelegate {
-            validator: delegations[0].validator.clone(),
+        .add_message(StakingMsg::Delegate {
+            validator,
             amount: coin.clone(),
         })
         .add_attribute("method", "execute")
-        .add_attribute("action", "undelegate"))
+        .add_attribute("action", "delegate")
+        .add_attribute("amount", coin.amount))
+}
+
+fn execute_redelegate(
+    deps: DepsMut,
+    env: Env,
+    dst_validator: String,
+) -> Result<Response, ContractError> {
+    let src_validator = VALIDATOR.load(deps.storage)?;
+    let delegation = deps
+        .querier
+        .query_delegation(env.contract.address, src_validator.clone())?
+        .ok_or(ContractError::NoDelegation {})?;
+    VALIDATOR.save(deps.storage, &dst_validator)?;
+
+    Ok(Response::new()
+        .add_message(StakingMsg::Redelegate {
+            src_validator,
+            dst_validator,
+            amount: delegation.amount,
+        })
+        .add_attribute("method", "execute")
+        .add_attribute("action", "redelegate"))
+}
+
+fn execute_claim(
+    deps: DepsMut,
+    recipient: String,
+    ids: Vec<u64>,
+) -> Result<Response, ContractError> {
+    let mut unbondings = UNBONDINGS.may_load(deps.storage)?.unwrap_or_default();
+    let released = RELEASED.may_load(deps.storage)?.unwrap_or_default();
+
+    // only what the chain paid for these unbondings goes out, the others keep waiting
+    let mut claimed = Uint128::zero();
+    for id in ids {
+        let index = unbondings
+            .iter()
+            .position(|unbonding| unbonding.id == id)
+            .ok_or(ContractError::UnknownUnbonding { id })?;
+        if !unbondings[index].released {
+            return Err(ContractError::UnbondingNotReleased { id });
+        }
+        claimed += unbondings.remove(index).amount;
+    }
+    UNBONDINGS.save(deps.storage, &unbondings)?;
+    RELEASED.save(deps.storage, &(released - claimed))?;
+
+    let mut res = Response::new();
+    if !claimed.is_zero() {
+        res = res.add_message(BankMsg::Send {
+            to_address: recipient,
+            amount: vec![Coin {
+                denom: deps.querier.query_bonded_denom()?,
+                amount: claimed,
+            }],
+        });
+    }
+
+    Ok(res
+        .add_attribute("method", "execute")
+        .add_attribute("action", "claim")
+        .add_attribute("amount", claimed))
+}
+
+/// Stores the slashes and releases of the pending unbondings.
+fn update_unbondings(deps: DepsMut, env: &Env) -> StdResult<()> {
+    let mut unbondings = UNBONDINGS.may_load(deps.storage)?.unwrap_or_default();
+    if unbondings.iter().all(|unbonding| unbonding.released) {
+        return Ok(());
+    }
+
+    let mut released = RELEASED.may_load(deps.storage)?.unwrap_or_default();
+    sync_unbondings(deps.as_ref(), env, &mut unbondings, &mut released)?;
+    UNBONDINGS.save(deps.storage, &unbondings)?;
+    RELEASED.save(deps.storage, &released)
+}
+
+/// Brings `unbondings` in line with the entries of the staking module, and adds what the
+/// chain released for them to `released`.
+fn sync_unbondings(
+    deps: Deps,
+    env: &Env,
+    unbondings: &mut [Unbonding],
+    released: &mut Uint128,
+) -> StdResult<()> {
+    if unbondings.iter().all(|unbonding| unbonding.released) {
+        return Ok(());
+    }
+
+    let entries = query_unbonding_entries(deps.querier, env.contract.address.as_str())?;
+    let bonded_denom = deps.querier.query_bonded_denom()?;
+    let balance = deps
+        .querier
+        .query_balance(&env.contract.address, bonded_denom)?
+        .amount;
+    apply_unbonding_entries(unbondings, &entries, balance, released);
+    Ok(())
+}
+
+/// Undelegations of the same block share an entry, whose balance is split by their initial
+/// amounts, so a slash only lowers the unbondings it hit. Unbondings the chain no longer reports
+/// were released to this contract, which holds `balance` of the bonded denom.
+fn apply_unbonding_entries(
+    unbondings: &mut [Unbonding],
+    entries: &[UnbondingEntry],
+    balance: Uint128,
+    released: &mut Uint128,
+) {
+    let mut newly_released = vec![];
+    for (index, unbonding) in unbondings.iter_mut().enumerate() {
+        if unbonding.released {
+            continue;
+        }
+        let (initial, left) = entries
+            .iter()
+            .filter(|entry| {
+                entry.validator == unbonding.validator
+                    && entry.creation_height == unbonding.creation_height
+            })
+            .fold(
+                (Uint128::zero(), Uint128::zero()),
+                |(initial, left), entry| (initial + entry.initial_balance, left + entry.balance),
+            );
+        if initial.is_zero() {
+            newly_released.push(index);
+        } else {
+            unbonding.amount = unbonding.initial_amount.multiply_ratio(left, initial);
+        }
+    }
+
+    // a slash between the last sync and the release is only visible in the balance,
+    // so it is shared by the unbondings released meanwhile
+    let expected: Uint128 = newly_released
+        .iter()
+        .map(|&index| unbondings[index].amount)
+        .sum();
+    let available = balance.saturating_sub(*released);
+    for index in newly_released {
+        let unbonding = &mut unbondings[index];
+        if available < expected {
+            unbonding.amount = unbonding.amount.multiply_ratio(available, expected);
+        }
+        unbonding.released = true;
+        *released += unbonding.amount;
+    }
 }
 
 /// Handling contract query
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(_deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
-        // Find matched incoming message variant and query them your custom logic
-        // and then construct your query response with the type usually defined
-        // `msg.rs` alongside with the query message itself.
-        //
-        // use `cosmwasm_std::to_binary` to serialize query response to json binary.
+        QueryMsg::Config {} => to_json_binary(&query_config(deps)?),
+        QueryMsg::Delegation {} => to_json_binary(&query_delegation(deps, env)?),
+        QueryMsg::PendingRewards {} => to_json_binary(&query_pending_rewards(deps, env)?),
+        QueryMsg::Unbondings {} => to_json_binary(&query_unbondings(deps, env)?),
+        QueryMsg::IdleBalance {} => to_json_binary(&query_idle_balance(deps, env)?),
     }
 }
 
+fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let ownership = cw_ownable::get_ownership(deps.storage)?;
+    Ok(ConfigResponse {
+        owner: ownership.owner.map(String::from),
+        validator: VALIDATOR.load(deps.storage)?,
+    })
+}
+
+fn query_delegation(deps: Deps, env: Env) -> StdResult<DelegationResponse> {
+    let validator = VALIDATOR.load(deps.storage)?;
+    let amount = match deps
+        .querier
+        .query_delegation(env.contract.address, validator.clone())?
+    {
+        Some(delegation) => delegation.amount,
+        None => Coin {
+            denom: deps.querier.query_bonded_denom()?,
+            amount: Uint128::zero(),
+        },
+    };
+    Ok(DelegationResponse { validator, amount })
+}
+
+fn query_pending_rewards(deps: Deps, env: Env) -> StdResult<PendingRewardsResponse> {
+    let validator = VALIDATOR.load(deps.storage)?;
+    let rewards = deps
+        .querier
+        .query_delegation(env.contract.address, validator)?
+        .map(|delegation| delegation.accumulated_rewards)
+        .unwrap_or_default();
+    Ok(PendingRewardsResponse { rewards })
+}
+
+fn query_unbondings(deps: Deps, env: Env) -> StdResult<UnbondingsResponse> {
+    let mut unbondings = UNBONDINGS.may_load(deps.storage)?.unwrap_or_default();
+    let mut released = RELEASED.may_load(deps.storage)?.unwrap_or_default();
+    sync_unbondings(deps, &env, &mut unbondings, &mut released)?;
+
+    let unbondings = unbondings
+        .into_iter()
+        .map(|unbonding| UnbondingInfo {
+            id: unbonding.id,
+            initial_amount: unbonding.initial_amount,
+            amount: unbonding.amount,
+            start_time: unbonding.start_time,
+            completion_time: unbonding.completion_time,
+            released: unbonding.released,
+        })
+        .collect();
+    Ok(UnbondingsResponse { unbondings })
+}
+
+fn query_idle_balance(deps: Deps, env: Env) -> StdResult<IdleBalanceResponse> {
+    let bonded_denom = deps.querier.query_bonded_denom()?;
+    let balance = deps
+        .querier
+        .query_balance(env.contract.address, bonded_denom)?;
+    Ok(IdleBalanceResponse { balance })
+}
+
 /// Handling submessage reply.
 /// For more info on submessage and reply, see https://github.com/CosmWasm/cosmwasm/blob/main/SEMANTICS.md#submessages
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -132,3 +535,346 @@ pub fn reply(_deps: DepsMut, _env: Env, _msg: Reply) -> Result<Response, Contrac
 
     todo!()
 }
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+        MOCK_CONTRACT_ADDR,
+    };
+    use cosmwasm_std::{coin, coins, Addr, FullDelegation, OwnedDeps};
+    use cw_ownable::OwnershipError;
+
+    use super::*;
+
+    const DENOM: &str = "cony";
+    const VALIDATOR_ADDR: &str = "validator";
+    const UNBONDING_TIME: u64 = 60 * 60 * 24 * 21;
+
+    fn completion_time() -> Timestamp {
+        mock_env().block.time.plus_seconds(UNBONDING_TIME)
+    }
+
+    fn delegation(amount: u128) -> FullDelegation {
+        FullDelegation {
+            delegator: Addr::unchecked(MOCK_CONTRACT_ADDR),
+            validator: VALIDATOR_ADDR.to_string(),
+            amount: coin(amount, DENOM),
+            can_redelegate: coin(amount, DENOM),
+            accumulated_rewards: coins(10, DENOM),
+        }
+    }
+
+    fn setup(delegations: &[FullDelegation]) -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        deps.querier.update_staking(DENOM, &[], delegations);
+        let msg = InstantiateMsg {
+            validator: VALIDATOR_ADDR.to_string(),
+        };
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("portal", &coins(1_000, DENOM)),
+            msg,
+        )
+        .unwrap();
+        deps
+    }
+
+    #[test]
+    fn execute_rejects_other_senders() {
+        let mut deps = setup(&[delegation(1_000)]);
+
+        let msg = ExecuteMsg::Undelegate {
+            id: 0,
+            amount: Uint128::new(100),
+            completion_time: completion_time(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("other", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Ownership(OwnershipError::NotOwner));
+    }
+
+    #[test]
+    fn undelegate_without_delegation() {
+        let mut deps = setup(&[]);
+
+        let msg = ExecuteMsg::Undelegate {
+            id: 0,
+            amount: Uint128::new(100),
+            completion_time: completion_time(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("portal", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::NoDelegation {});
+    }
+
+    #[test]
+    fn undelegate_more_than_delegated() {
+        let mut deps = setup(&[delegation(1_000)]);
+
+        let msg = ExecuteMsg::Undelegate {
+            id: 0,
+            amount: Uint128::new(1_001),
+            completion_time: completion_time(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("portal", &[]), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InsufficientDelegation {
+                requested: Uint128::new(1_001),
+                available: Uint128::new(1_000),
+            }
+        );
+
+        // the whole delegation can be undelegated
+        let msg = ExecuteMsg::Undelegate {
+            id: 0,
+            amount: Uint128::new(1_000),
+            completion_time: completion_time(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("portal", &[]), msg).unwrap();
+    }
+
+    #[test]
+    fn withdraw_reward_without_delegation() {
+        let mut deps = setup(&[]);
+
+        let msg = ExecuteMsg::WithdrawReward {
+            recipient: "token".to_string(),
+            fee: None,
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("portal", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::NoDelegation {});
+    }
+
+    #[test]
+    fn compound_forwards_other_denoms() {
+        let mut deps = setup(&[FullDelegation {
+            accumulated_rewards: vec![coin(100, DENOM), coin(50, "uatom")],
+            ..delegation(1_000)
+        }]);
+
+        let msg = ExecuteMsg::Compound {
+            recipient: "owner".to_string(),
+            fee: Some(Fee {
+                bps: 1_000,
+                recipient: "treasury".to_string(),
+            }),
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("portal", &[]), msg).unwrap();
+        assert_eq!(
+            res.messages
+                .into_iter()
+                .map(|msg| msg.msg)
+                .collect::<Vec<_>>(),
+            vec![
+                DistributionMsg::WithdrawDelegatorReward {
+                    validator: VALIDATOR_ADDR.to_string(),
+                }
+                .into(),
+                BankMsg::Send {
+                    to_address: "treasury".to_string(),
+                    amount: vec![coin(10, DENOM), coin(5, "uatom")],
+                }
+                .into(),
+                StakingMsg::Delegate {
+                    validator: VALIDATOR_ADDR.to_string(),
+                    amount: coin(90, DENOM),
+                }
+                .into(),
+                BankMsg::Send {
+                    to_address: "owner".to_string(),
+                    amount: coins(45, "uatom"),
+                }
+                .into(),
+            ]
+        );
+        let fee_amount = format!("10{},5uatom", DENOM);
+        assert!(res
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "fee_amount" && attr.value == fee_amount));
+    }
+
+    fn unbonding(id: u64, creation_height: u64, amount: u128) -> Unbonding {
+        Unbonding {
+            id,
+            validator: VALIDATOR_ADDR.to_string(),
+            creation_height,
+            initial_amount: Uint128::new(amount),
+            amount: Uint128::new(amount),
+            start_time: mock_env().block.time,
+            completion_time: completion_time(),
+            released: false,
+        }
+    }
+
+    fn entry(creation_height: u64, initial_balance: u128, balance: u128) -> UnbondingEntry {
+        UnbondingEntry {
+            validator: VALIDATOR_ADDR.to_string(),
+            creation_height,
+            initial_balance: Uint128::new(initial_balance),
+            balance: Uint128::new(balance),
+        }
+    }
+
+    #[test]
+    fn slash_hits_its_unbonding() {
+        let mut unbondings = vec![
+            unbonding(0, 10, 300),
+            unbonding(1, 10, 100),
+            unbonding(2, 20, 200),
+        ];
+        let mut released = Uint128::zero();
+
+        // the undelegations of block 10 share an entry, only it was slashed by 10%
+        apply_unbonding_entries(
+            &mut unbondings,
+            &[entry(10, 400, 360), entry(20, 200, 200)],
+            Uint128::zero(),
+            &mut released,
+        );
+        let amounts: Vec<_> = unbondings.iter().map(|u| u.amount.u128()).collect();
+        assert_eq!(amounts, vec![270, 90, 200]);
+        assert!(unbondings.iter().all(|u| !u.released));
+        assert_eq!(released, Uint128::zero());
+
+        // block 10 is released, the slashed amounts are paid
+        apply_unbonding_entries(
+            &mut unbondings,
+            &[entry(20, 200, 200)],
+            Uint128::new(360),
+            &mut released,
+        );
+        let released_ids: Vec<_> = unbondings
+            .iter()
+            .filter(|u| u.released)
+            .map(|u| u.id)
+            .collect();
+        assert_eq!(released_ids, vec![0, 1]);
+        assert_eq!(released, Uint128::new(360));
+        assert_eq!(unbondings[2].amount, Uint128::new(200));
+    }
+
+    #[test]
+    fn release_after_unobserved_slash() {
+        let mut unbondings = vec![unbonding(0, 10, 300), unbonding(1, 20, 200)];
+        let mut released = Uint128::zero();
+
+        // the chain paid 10% less than recorded, the shortfall is shared
+        apply_unbonding_entries(&mut unbondings, &[], Uint128::new(450), &mut released);
+        let amounts: Vec<_> = unbondings.iter().map(|u| u.amount.u128()).collect();
+        assert_eq!(amounts, vec![270, 180]);
+        assert!(unbondings.iter().all(|u| u.released));
+        assert_eq!(released, Uint128::new(450));
+    }
+
+    #[test]
+    fn claim_released_unbondings() {
+        let mut deps = setup(&[delegation(1_000)]);
+        let mut unbondings = vec![unbonding(0, 10, 300), unbonding(1, 20, 200)];
+        for unbonding in unbondings.iter_mut() {
+            unbonding.released = true;
+        }
+        UNBONDINGS.save(deps.as_mut().storage, &unbondings).unwrap();
+        RELEASED
+            .save(deps.as_mut().storage, &Uint128::new(500))
+            .unwrap();
+
+        let claim = |ids: Vec<u64>| ExecuteMsg::Claim {
+            recipient: "user".to_string(),
+            ids,
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("portal", &[]),
+            claim(vec![1, 2]),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::UnknownUnbonding { id: 2 });
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("portal", &[]),
+            claim(vec![1]),
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            BankMsg::Send {
+                to_address: "user".to_string(),
+                amount: coins(200, DENOM),
+            }
+            .into()
+        );
+        assert_eq!(RELEASED.load(&deps.storage).unwrap(), Uint128::new(300));
+        let unbondings = UNBONDINGS.load(&deps.storage).unwrap();
+        assert_eq!(unbondings.len(), 1);
+        assert_eq!(unbondings[0].id, 0);
+    }
+
+    #[test]
+    fn undelegate_with_duplicate_id() {
+        let mut deps = setup(&[delegation(1_000)]);
+        let unbonding = Unbonding {
+            released: true,
+            ..unbonding(7, 10, 100)
+        };
+        UNBONDINGS
+            .save(deps.as_mut().storage, &vec![unbonding.clone()])
+            .unwrap();
+
+        let msg = ExecuteMsg::Undelegate {
+            id: 7,
+            amount: Uint128::new(100),
+            completion_time: completion_time(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("portal", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::DuplicateUnbonding { id: 7 });
+        assert_eq!(UNBONDINGS.load(&deps.storage).unwrap(), vec![unbonding]);
+    }
+
+    #[test]
+    fn instantiate_without_funds() {
+        let mut deps = mock_dependencies();
+        deps.querier.update_staking(DENOM, &[], &[]);
+
+        for funds in [vec![], coins(1_000, "uatom")] {
+            let err = instantiate(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("portal", &funds),
+                InstantiateMsg {
+                    validator: VALIDATOR_ADDR.to_string(),
+                },
+            )
+            .unwrap_err();
+            assert_eq!(
+                err,
+                ContractError::NoFunds {
+                    denom: DENOM.to_string(),
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn delegate_without_bonded_denom() {
+        let mut deps = setup(&[delegation(1_000)]);
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("portal", &coins(1_000, "uatom")),
+            ExecuteMsg::Delegate {},
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::NoFunds {
+                denom: DENOM.to_string(),
+            }
+        );
+    }
+}
@@ -1,16 +1,41 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Uint128};
+use cw_ownable::OwnershipError;
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, PartialEq)]
 pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error(transparent)]
+    Ownership(#[from] OwnershipError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
     #[error("Custom Error val: {val:?}")]
     CustomError { val: String },
+
+    #[error("Nothing is delegated")]
+    NoDelegation {},
+
+    #[error("Cannot undelegate {requested}, only {available} is delegated")]
+    InsufficientDelegation {
+        requested: Uint128,
+        available: Uint128,
+    },
+
+    #[error("No {denom} sent")]
+    NoFunds { denom: String },
+
+    #[error("Unbonding {id} already exists")]
+    DuplicateUnbonding { id: u64 },
+
+    #[error("Unknown unbonding {id}")]
+    UnknownUnbonding { id: u64 },
+
+    #[error("Unbonding {id} is not released by the chain yet")]
+    UnbondingNotReleased { id: u64 },
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
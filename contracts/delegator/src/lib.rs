@@ -1,6 +1,7 @@
 pub mod contract;
 mod error;
 pub mod msg;
+pub mod querier;
 pub mod state;
 
 pub use crate::error::ContractError;
@@ -1,5 +1,5 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::Uint128;
+use cosmwasm_std::{Coin, Timestamp, Uint128};
 
 /// Message type for `instantiate` entry_point
 #[cw_serde]
@@ -7,30 +7,114 @@ pub struct InstantiateMsg {
     pub validator: String,
 }
 
+/// Share of the withdrawn rewards paid to `recipient`, in basis points
+#[cw_serde]
+pub struct Fee {
+    pub bps: u16,
+    pub recipient: String,
+}
+
 /// Message type for `execute` entry_point
 #[cw_serde]
 pub enum ExecuteMsg {
-    WithdrawReward { recipient: String },
-    Undelegate { amount: Uint128 },
+    /// Withdraw rewards to this contract and pay them out, minus the fee
+    WithdrawReward {
+        recipient: String,
+        fee: Option<Fee>,
+    },
+    /// Undelegate `amount` as unbonding `id`, claimable once the chain releases it,
+    /// which is not before `completion_time`
+    Undelegate {
+        id: u64,
+        amount: Uint128,
+        completion_time: Timestamp,
+    },
+    /// Delegate the attached coins to the validator
+    Delegate {},
+    /// Move the whole delegation to another validator
+    Redelegate {
+        dst_validator: String,
+    },
+    /// Withdraw rewards to this contract and delegate them, minus the fee, to the same validator.
+    /// Rewards in other denoms cannot be delegated and are paid to the recipient instead
+    Compound {
+        recipient: String,
+        fee: Option<Fee>,
+    },
+    /// Send what the chain released for the unbondings `ids` to the recipient.
+    /// Fails if any of them is unknown or not released yet
+    Claim {
+        recipient: String,
+        ids: Vec<u64>,
+    },
 }
 
 /// Message type for `migrate` entry_point
 #[cw_serde]
-pub enum MigrateMsg {}
+pub struct MigrateMsg {}
 
 /// Message type for `query` entry_point
 #[cw_serde]
 #[derive(QueryResponses)]
 pub enum QueryMsg {
-    // This example query variant indicates that any client can query the contract
-    // using `YourQuery` and it will return `YourQueryResponse`
-    // This `returns` information will be included in contract's schema
-    // which is used for client code generation.
-    //
-    // #[returns(YourQueryResponse)]
-    // YourQuery {},
-}
-
-// We define a custom struct for each query response
-// #[cw_serde]
-// pub struct YourQueryResponse {}
+    #[returns(ConfigResponse)]
+    Config {},
+
+    /// Amount currently bonded to the validator
+    #[returns(DelegationResponse)]
+    Delegation {},
+
+    /// Rewards accumulated by the delegation and not withdrawn yet
+    #[returns(PendingRewardsResponse)]
+    PendingRewards {},
+
+    /// Undelegations not claimed yet, oldest first, with the slashes and releases
+    /// the staking module reports
+    #[returns(UnbondingsResponse)]
+    Unbondings {},
+
+    /// Bonded denom held by this contract, such as unbonded coins waiting to be claimed
+    #[returns(IdleBalanceResponse)]
+    IdleBalance {},
+}
+
+#[cw_serde]
+pub struct ConfigResponse {
+    pub owner: Option<String>,
+    pub validator: String,
+}
+
+#[cw_serde]
+pub struct DelegationResponse {
+    pub validator: String,
+    pub amount: Coin,
+}
+
+#[cw_serde]
+pub struct PendingRewardsResponse {
+    pub rewards: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct UnbondingsResponse {
+    pub unbondings: Vec<UnbondingInfo>,
+}
+
+#[cw_serde]
+pub struct UnbondingInfo {
+    pub id: u64,
+    pub initial_amount: Uint128,
+    /// Left after slashes, paid out by a claim
+    pub amount: Uint128,
+    /// Completes after the unbonding time of the chain
+    pub start_time: Timestamp,
+    /// Released by the chain at the end of the block that reaches it
+    pub completion_time: Timestamp,
+    /// Claimable once set
+    pub released: bool,
+}
+
+#[cw_serde]
+pub struct IdleBalanceResponse {
+    pub balance: Coin,
+}
@@ -0,0 +1,109 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_json_vec, Binary, ContractResult, QuerierWrapper, QueryRequest, StdError, StdResult,
+    SystemResult, Uint128,
+};
+use prost;
+use prost::Message;
+
+#[cw_serde]
+pub struct CustomQuery {
+    pub path: String,
+    pub data: Binary,
+}
+
+impl cosmwasm_std::CustomQuery for CustomQuery {}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct QueryDelegatorUnbondingDelegationsRequest {
+    #[prost(string, tag = "1")]
+    pub delegator_addr: ::prost::alloc::string::String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct QueryDelegatorUnbondingDelegationsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub unbonding_responses: ::prost::alloc::vec::Vec<UnbondingDelegation>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct UnbondingDelegation {
+    #[prost(string, tag = "1")]
+    pub delegator_address: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub validator_address: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "3")]
+    pub entries: ::prost::alloc::vec::Vec<UnbondingDelegationEntry>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct UnbondingDelegationEntry {
+    #[prost(int64, tag = "1")]
+    pub creation_height: i64,
+    #[prost(string, tag = "3")]
+    pub initial_balance: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub balance: ::prost::alloc::string::String,
+}
+
+/// Unbonding the staking module has not released yet
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnbondingEntry {
+    pub validator: String,
+    pub creation_height: u64,
+    pub initial_balance: Uint128,
+    /// What is left of `initial_balance` after slashes
+    pub balance: Uint128,
+}
+
+pub fn query_unbonding_entries(
+    querier: QuerierWrapper,
+    delegator: &str,
+) -> StdResult<Vec<UnbondingEntry>> {
+    let value = query(
+        querier,
+        &QueryRequest::Custom(CustomQuery {
+            path: "/cosmos.staking.v1beta1.Query/DelegatorUnbondingDelegations".to_string(),
+            data: QueryDelegatorUnbondingDelegationsRequest {
+                delegator_addr: delegator.to_string(),
+            }
+            .encode_to_vec()
+            .into(),
+        }),
+    );
+
+    let res = match QueryDelegatorUnbondingDelegationsResponse::decode(&*value?.to_vec()) {
+        Ok(res) => Ok(res),
+        Err(err) => Err(StdError::GenericErr {
+            msg: err.to_string(),
+        }),
+    };
+
+    let mut entries = vec![];
+    for unbonding in res?.unbonding_responses {
+        for entry in unbonding.entries {
+            entries.push(UnbondingEntry {
+                validator: unbonding.validator_address.clone(),
+                creation_height: entry.creation_height as u64,
+                initial_balance: entry.initial_balance.parse()?,
+                balance: entry.balance.parse()?,
+            });
+        }
+    }
+    Ok(entries)
+}
+
+pub fn query(querier: QuerierWrapper, request: &QueryRequest<CustomQuery>) -> StdResult<Binary> {
+    let raw = to_json_vec(request).map_err(|serialize_err| {
+        StdError::generic_err(format!("Serializing QueryRequest: {serialize_err}"))
+    })?;
+    match querier.raw_query(&raw) {
+        SystemResult::Err(system_err) => Err(StdError::generic_err(format!(
+            "Querier system error: {system_err}"
+        ))),
+        SystemResult::Ok(ContractResult::Err(contract_err)) => Err(StdError::generic_err(format!(
+            "Querier contract error: {contract_err}"
+        ))),
+        SystemResult::Ok(ContractResult::Ok(value)) => Ok(value),
+    }
+}
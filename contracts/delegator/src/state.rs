@@ -1,2 +1,30 @@
 // use `cw_storage_plus` to create ORM-like interface to storage
 // see: https://crates.io/crates/cw-storage-plus
+use cosmwasm_std::{Timestamp, Uint128};
+use cw_storage_plus::Item;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Undelegation waiting to be claimed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Unbonding {
+    /// Chosen by the owner, which claims by it
+    pub id: u64,
+    pub validator: String,
+    /// Block height of the undelegation, which identifies its entry on the staking module
+    pub creation_height: u64,
+    pub initial_amount: Uint128,
+    /// What the chain pays out, less than `initial_amount` after slashes
+    pub amount: Uint128,
+    pub start_time: Timestamp,
+    pub completion_time: Timestamp,
+    /// Set once the chain paid `amount` to this contract
+    pub released: bool,
+}
+
+/// Validator this contract delegates to
+pub const VALIDATOR: Item<String> = Item::new("validator");
+/// Oldest first
+pub const UNBONDINGS: Item<Vec<Unbonding>> = Item::new("unbondings");
+/// Bonded denom the chain released for `UNBONDINGS` that was not claimed yet
+pub const RELEASED: Item<Uint128> = Item::new("released");
@@ -1,21 +1,37 @@
+use std::collections::BTreeMap;
+
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    instantiate2_address, to_json_binary, Addr, Api, Binary, CanonicalAddr, CosmosMsg, Deps,
-    DepsMut, Empty, Env, MessageInfo, Order, QuerierWrapper, Reply, Response, StdError, StdResult,
-    Storage, Uint128, WasmMsg,
+    coin, from_json, instantiate2_address, to_json_binary, Addr, Api, Binary, CanonicalAddr, Coin,
+    CosmosMsg, Decimal, Deps, DepsMut, Empty, Env, MessageInfo, Order, QuerierWrapper, Reply,
+    Response, StdError, StdResult, Storage, SubMsg, SubMsgResponse, Timestamp, Uint128, WasmMsg,
 };
-use cw2::set_contract_version;
-use cw20::Cw20Coin;
+use cw2::{ensure_from_older_version, set_contract_version};
+use cw20::{Cw20Coin, Cw20ReceiveMsg, MinterResponse};
+use cw721::Cw721ReceiveMsg;
+use cw_storage_plus::{Bound, KeyDeserialize};
 use prost::Message;
 
-use crate::cosmos_msg::{CosmosCoin, MsgInstantiateContract2};
+use crate::cosmos_msg::{CosmosCoin, MsgInstantiateContract2, MsgInstantiateContract2Response};
 use crate::error::ContractError;
+use crate::metadata::record_token_uri;
 use crate::msg::{
-    AllResponse, DataResponse, EnvResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg,
+    AllResponse, ClaimInfo, ClaimsResponse, DataResponse, EnvResponse, ExchangeRateResponse,
+    ExecuteMsg, FeeConfigResponse, InstantiateMsg, MigrateMsg, NftReceiveMsg, PositionResponse,
+    QueryMsg, ReceiveMsg, TokenMetadataResponse, ValidatorAllowListResponse,
+};
+use crate::position_id::PositionId;
+use crate::querier::{
+    query_unbonding_time, query_validator_info, query_wasm_code_hash, ValidatorInfo,
+    BOND_STATUS_BONDED,
+};
+use crate::state::{
+    positions, v0_1, FeeConfig, LiquidStakingData, PendingForward, PortalEnv, TokenMetadata,
+    Unbonding, CLOSED_POSITIONS, FEE_CONFIG, PENDING_FORWARD, PENDING_LS_DATA, PORTAL_ENV,
+    POSITION_SEQ, TOKEN_METADATA, UNBONDINGS, UNBONDING_SEQ, VALIDATOR_ALLOW_LIST,
+    VALIDATOR_ALLOW_LIST_ENABLED,
 };
-use crate::querier::query_wasm_code_hash;
-use crate::state::{LiquidStakingData, PortalEnv, LS_DATA, PORTAL_ENV};
 use sha2::{
     digest::{Digest, Update},
     Sha256,
@@ -25,9 +41,21 @@ use sha2::{
 const CONTRACT_NAME: &str = "crates.io:portal";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+// records harvested by a single WithdrawAllReward, and queried per cw721 page
+const WITHDRAW_ALL_REWARD_MAX_RECORDS: u32 = 100;
+const WITHDRAW_ALL_REWARD_PAGE_LIMIT: u32 = 30;
+
+// page size of the position queries
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+// upper bound of the protocol fee, in basis points
+pub const MAX_FEE_BPS: u16 = 3_000;
+
 // callback id
 pub const EXEC_DELEGATE_AND_TOKENIZE_CALLBACK_ID_1: u64 = 1;
 pub const EXEC_DELEGATE_AND_TOKENIZE_CALLBACK_ID_2: u64 = 2;
+pub const EXEC_FORWARD_REWARDS_CALLBACK_ID: u64 = 3;
 
 /// Handling contract instantiation
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -38,6 +66,7 @@ pub fn instantiate(
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    cw_ownable::initialize_owner(deps.storage, deps.api, Some(info.sender.as_str()))?;
 
     let creator = deps.api.addr_canonicalize(env.contract.address.as_str())?;
     let salt = b"instantiate";
@@ -71,6 +100,7 @@ pub fn instantiate(
             cw20_code_id: msg.cw20_code_id,
             cw721_address: cw721_address.to_string(),
             delegator_code_id: msg.delegator_code_id,
+            min_delegation: msg.min_delegation.unwrap_or(Uint128::one()),
         },
     )?;
 
@@ -85,18 +115,58 @@ pub fn instantiate(
 }
 
 /// Handling contract migration
-/// To make a contract migratable, you need
-/// - this entry_point implemented
-/// - only contract admin can migrate, so admin has to be set at contract initiation time
-/// Handling contract execution
+/// Only the contract admin can migrate. Downgrades and other contracts are rejected.
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(_deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
-    match msg {
-        // Find matched incoming message variant and execute them with your custom logic.
-        //
-        // With `Response` type, it is possible to dispatch message to invoke external logic.
-        // See: https://github.com/CosmWasm/cosmwasm/blob/main/SEMANTICS.md#dispatching-messages
+pub fn migrate(mut deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    let original_version =
+        ensure_from_older_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    let mut res = Response::new();
+    if original_version < semver::Version::new(0, 2, 0) {
+        let cw721_code_id = msg
+            .cw721_code_id
+            .ok_or(ContractError::MissingCw721CodeId {})?;
+        res = res.add_message(migrate_from_v0_1(deps.branch(), &env, cw721_code_id)?);
     }
+
+    Ok(res
+        .add_attribute("method", "migrate")
+        .add_attribute("from_version", original_version.to_string())
+        .add_attribute("to_version", CONTRACT_VERSION))
+}
+
+/// 0.1.x had no owner, no minimum delegation, did not store the validator of a record and
+/// minted records without metadata. Returns the migration of the cw721, whose admin is the portal.
+fn migrate_from_v0_1(
+    deps: DepsMut,
+    env: &Env,
+    cw721_code_id: u64,
+) -> Result<WasmMsg, ContractError> {
+    let admin = deps
+        .querier
+        .query_wasm_contract_info(&env.contract.address)?
+        .admin;
+    cw_ownable::initialize_owner(deps.storage, deps.api, admin.as_deref())?;
+
+    let legacy_env = v0_1::PORTAL_ENV.load(deps.storage)?;
+    PORTAL_ENV.save(
+        deps.storage,
+        &PortalEnv {
+            cw20_code_id: legacy_env.cw20_code_id,
+            cw721_address: legacy_env.cw721_address.clone(),
+            delegator_code_id: legacy_env.delegator_code_id,
+            min_delegation: Uint128::one(),
+        },
+    )?;
+
+    // records are moved in batches by `MigrateLegacyPositions`, which also fills in their metadata
+    v0_1::MIGRATION_CURSOR.save(deps.storage, &None)?;
+
+    Ok(WasmMsg::Migrate {
+        contract_addr: legacy_env.cw721_address,
+        new_code_id: cw721_code_id,
+        msg: to_json_binary(&Empty {})?,
+    })
 }
 
 /// Handling contract execution
@@ -111,8 +181,69 @@ pub fn execute(
         ExecuteMsg::DelegateAndTokenize { validator } => {
             execute_delegate_and_tokenize(deps, env, info, validator)
         }
-        ExecuteMsg::WithdrawAllReward {} => execute_withdraw_all_reward(deps, info),
-        ExecuteMsg::Undelegate { id, amount } => execute_undelegate(deps, info, id, amount),
+        ExecuteMsg::WithdrawAllReward { start_after, limit } => {
+            execute_withdraw_all_reward(deps, info, start_after, limit)
+        }
+        ExecuteMsg::WithdrawReward { id } => execute_withdraw_rewards(deps, info, vec![id]),
+        ExecuteMsg::WithdrawRewards { ids } => execute_withdraw_rewards(deps, info, ids),
+        ExecuteMsg::Compound { id } => execute_compound(deps, info, id),
+        ExecuteMsg::Delegate { id } => execute_delegate(deps, info, id),
+        ExecuteMsg::Redelegate { id, dst_validator } => {
+            execute_redelegate(deps, info, id, dst_validator)
+        }
+        ExecuteMsg::Undelegate { id, amount } => {
+            undelegate(deps, env, info.sender, id, amount, false)
+        }
+        ExecuteMsg::Receive(msg) => execute_receive(deps, env, info, msg),
+        ExecuteMsg::ReceiveNft(msg) => execute_receive_nft(deps, env, info, msg),
+        ExecuteMsg::Claim {} => execute_claim(deps, env, info),
+        ExecuteMsg::UpdateValidatorAllowList {
+            enabled,
+            add,
+            remove,
+        } => execute_update_validator_allow_list(deps, info, enabled, add, remove),
+        ExecuteMsg::UpdateConfig {
+            cw20_code_id,
+            delegator_code_id,
+            min_delegation,
+        } => execute_update_config(deps, info, cw20_code_id, delegator_code_id, min_delegation),
+        ExecuteMsg::UpdateFeeConfig { fee_bps, treasury } => {
+            execute_update_fee_config(deps, info, fee_bps, treasury)
+        }
+        ExecuteMsg::UpdateTokenMetadata {
+            name_template,
+            symbol_template,
+            description_template,
+            logo_url,
+        } => execute_update_token_metadata(
+            deps,
+            info,
+            TokenMetadata {
+                name_template,
+                symbol_template,
+                description_template,
+                logo_url,
+            },
+        ),
+        ExecuteMsg::MigrateDelegators { ids, new_code_id } => {
+            execute_migrate_delegators(deps, info, ids, new_code_id)
+        }
+        ExecuteMsg::MigrateLegacyPositions {
+            cw20_code_id,
+            delegator_code_id,
+            limit,
+        } => execute_migrate_legacy_positions(
+            deps,
+            env,
+            info,
+            cw20_code_id,
+            delegator_code_id,
+            limit,
+        ),
+        ExecuteMsg::UpdateOwnership(action) => {
+            let ownership = cw_ownable::update_ownership(deps, &env.block, &info.sender, action)?;
+            Ok(Response::new().add_attributes(ownership.into_attributes()))
+        }
     }
 }
 
@@ -122,19 +253,25 @@ fn execute_delegate_and_tokenize(
     info: MessageInfo,
     validator: String,
 ) -> Result<Response, ContractError> {
-    // TODO: validate info
-    // TODO: validate msg
+    let portal_env = PORTAL_ENV.load(deps.storage)?;
+    let funds = validate_delegation_funds(deps.as_ref(), &info, portal_env.min_delegation)?;
+    let validator_info = validate_validator(deps.as_ref(), &validator)?;
 
-    let ls_data: StdResult<Vec<_>> = LS_DATA
-        .prefix(&validator.clone())
-        .range(deps.storage, None, None, Order::Ascending)
-        .collect();
-    let data_num = ls_data.unwrap().len();
-    let ls_id = validator.clone() + "/" + &data_num.to_string();
+    let seq = match POSITION_SEQ.may_load(deps.storage, &validator)? {
+        Some(seq) => seq,
+        // continue after the positions of 0.1.x, which did not count them
+        None => v0_1::LS_DATA
+            .prefix(&validator)
+            .keys(deps.storage, None, None, Order::Descending)
+            .next()
+            .transpose()?
+            .map_or(0, |seq| seq + 1),
+    };
+    POSITION_SEQ.save(deps.storage, &validator, &(seq + 1))?;
+    let ls_id = PositionId::new(validator.clone(), seq);
 
-    let portal_env = PORTAL_ENV.load(deps.storage)?;
     let creator = deps.api.addr_canonicalize(env.contract.address.as_str())?;
-    let salt = Sha256::digest(creator.to_string() + &ls_id.clone());
+    let salt = Sha256::digest(creator.to_string() + &ls_id.to_string());
     let delegator_address = instantiate_address(
         deps.api,
         deps.querier,
@@ -153,8 +290,8 @@ fn execute_delegate_and_tokenize(
         })?
         .to_vec(),
         funds: vec![CosmosCoin {
-            denom: info.funds[0].denom.clone(),
-            amount: info.funds[0].amount.to_string(),
+            denom: funds.denom.clone(),
+            amount: funds.amount.to_string(),
         }],
         label: "Liquid Staking Contract Delegator".to_string(),
         salt: salt.to_vec(),
@@ -169,16 +306,27 @@ fn execute_delegate_and_tokenize(
         &*salt,
     )?;
 
+    let metadata = TOKEN_METADATA.may_load(deps.storage)?.unwrap_or_default();
+    // a validator without a usable moniker must not keep anyone from delegating to it
+    let moniker = validator_info
+        .description
+        .map(|description| description.moniker);
+    let name = metadata.name(moniker.as_deref(), &ls_id);
+    let marketing = metadata.marketing(moniker.as_deref(), &ls_id);
     let cw20_init_msg = cw20_base::msg::InstantiateMsg {
-        name: "Liquid Staking Contract Token".to_string(),
-        symbol: "LSCT".to_string(),
+        name: name.clone(),
+        symbol: metadata.symbol(moniker.as_deref(), &ls_id),
         decimals: 6,
         initial_balances: vec![Cw20Coin {
             address: info.sender.to_string(),
-            amount: info.funds[0].amount.clone(),
+            amount: funds.amount,
         }],
-        mint: None,
-        marketing: None,
+        mint: Some(MinterResponse {
+            minter: env.contract.address.to_string(),
+            cap: None,
+        }),
+        marketing: marketing.clone(),
+        reward_denom: Some(funds.denom.clone()),
     };
 
     let cw20_wasm_init_msg = MsgInstantiateContract2 {
@@ -187,115 +335,976 @@ fn execute_delegate_and_tokenize(
         code_id: portal_env.cw20_code_id,
         msg: to_json_binary(&cw20_init_msg)?.to_vec(),
         funds: vec![],
-        label: "Liquid Staking Contract Token".to_string(),
+        label: name.clone(),
         salt: salt.to_vec(),
         fix_msg: false,
     };
 
-    // mint cw721
-    let cw721_mint_msg = cw721_base::msg::ExecuteMsg::<Empty, Empty>::Mint {
-        token_id: ls_id,
-        owner: info.sender.to_string(),
-        token_uri: None,
-        extension: Empty {},
+    // mint cw721, describing the stake for wallets
+    let record = cw721_base::Metadata {
+        name,
+        description: marketing.and_then(|marketing| marketing.description),
+        validator: validator.clone(),
+        initial_amount: funds.clone(),
+        delegated: funds.clone(),
+        token_address: cw20_address.to_string(),
+        delegator_address: delegator_address.to_string(),
+        created_height: env.block.height,
+        created_time: env.block.time,
     };
+    let cw721_mint_msg =
+        cw721_base::msg::ExecuteMsg::<cw721_base::Extension, cw721_base::ExtensionMsg>::Mint {
+            token_id: ls_id.to_string(),
+            owner: info.sender.to_string(),
+            token_uri: Some(record_token_uri(&record)?),
+            extension: Some(record),
+        };
     let cw721_wasm_exec_msg = WasmMsg::Execute {
         contract_addr: portal_env.cw721_address,
         msg: to_json_binary(&cw721_mint_msg)?,
         funds: vec![],
     };
 
-    LS_DATA.save(
-        deps.storage,
-        (&validator.clone(), data_num as u32),
-        &LiquidStakingData {
-            token_address: cw20_address.to_string(),
-            delegator_address: delegator_address.to_string(),
-        },
+    let ls_data = LiquidStakingData {
+        token_address: cw20_address.to_string(),
+        delegator_address: delegator_address.to_string(),
+        validator: validator.clone(),
+        created_at: env.block.height,
+    };
+    positions().save(deps.storage, ls_id.key(), &ls_data)?;
+    PENDING_LS_DATA.save(deps.storage, &ls_data)?;
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(
+            CosmosMsg::Stargate {
+                type_url: "/cosmwasm.wasm.v1.MsgInstantiateContract2".to_string(),
+                value: delegator_wasm_init_msg.encode_to_vec().into(),
+            },
+            EXEC_DELEGATE_AND_TOKENIZE_CALLBACK_ID_1,
+        ))
+        .add_submessage(SubMsg::reply_on_success(
+            CosmosMsg::Stargate {
+                type_url: "/cosmwasm.wasm.v1.MsgInstantiateContract2".to_string(),
+                value: cw20_wasm_init_msg.encode_to_vec().into(),
+            },
+            EXEC_DELEGATE_AND_TOKENIZE_CALLBACK_ID_2,
+        ))
+        .add_message(cw721_wasm_exec_msg)
+        .add_attribute("method", "execute")
+        .add_attribute("action", "delegate_and_tokenize"))
+}
+
+fn execute_delegate(
+    deps: DepsMut,
+    info: MessageInfo,
+    id: PositionId,
+) -> Result<Response, ContractError> {
+    let portal_env = PORTAL_ENV.load(deps.storage)?;
+    let funds = validate_delegation_funds(deps.as_ref(), &info, portal_env.min_delegation)?;
+    let data = load_ls_data(deps.storage, &id)?;
+    if CLOSED_POSITIONS.has(deps.storage, id.key()) {
+        return Err(ContractError::PositionClosed { id: id.to_string() });
+    }
+
+    // mint at the current exchange rate so existing holders keep their share
+    let (delegated, supply) = query_position_stake(deps.as_ref(), &data)?;
+    let minted = if delegated.is_zero() || supply.is_zero() {
+        funds.amount
+    } else {
+        funds.amount.multiply_ratio(supply, delegated)
+    };
+
+    let delegator_delegate_msg = delegator::msg::ExecuteMsg::Delegate {};
+    let cw20_mint_msg = cw20_base::msg::ExecuteMsg::Mint {
+        recipient: info.sender.to_string(),
+        amount: minted,
+    };
+    let update_record = update_record_msg(
+        deps.as_ref(),
+        &portal_env,
+        &id,
+        &data,
+        coin((delegated + funds.amount).u128(), &funds.denom),
     )?;
 
     Ok(Response::new()
-        .add_message(CosmosMsg::Stargate {
-            type_url: "/cosmwasm.wasm.v1.MsgInstantiateContract2".to_string(),
-            value: delegator_wasm_init_msg.encode_to_vec().into(),
+        .add_messages(harvest_msg(deps.as_ref(), &data)?)
+        .add_message(WasmMsg::Execute {
+            contract_addr: data.delegator_address,
+            msg: to_json_binary(&delegator_delegate_msg)?,
+            funds: vec![funds.clone()],
         })
-        .add_message(CosmosMsg::Stargate {
-            type_url: "/cosmwasm.wasm.v1.MsgInstantiateContract2".to_string(),
-            value: cw20_wasm_init_msg.encode_to_vec().into(),
+        .add_message(WasmMsg::Execute {
+            contract_addr: data.token_address,
+            msg: to_json_binary(&cw20_mint_msg)?,
+            funds: vec![],
         })
-        .add_message(cw721_wasm_exec_msg)
+        .add_messages(update_record)
         .add_attribute("method", "execute")
-        .add_attribute("action", "delegate_and_tokenize"))
+        .add_attribute("action", "delegate")
+        .add_attribute("id", id)
+        .add_attribute("amount", funds.amount)
+        .add_attribute("minted", minted))
 }
 
-fn execute_withdraw_all_reward(
+fn execute_redelegate(
     deps: DepsMut,
     info: MessageInfo,
+    id: PositionId,
+    dst_validator: String,
 ) -> Result<Response, ContractError> {
     let portal_env = PORTAL_ENV.load(deps.storage)?;
+    assert_record_owner(deps.as_ref(), &portal_env, &id, &info.sender)?;
+    validate_validator(deps.as_ref(), &dst_validator)?;
+
+    // the id keeps naming the original validator, the data follows the stake
+    let mut data = load_ls_data(deps.storage, &id)?;
+    let harvest = harvest_msg(deps.as_ref(), &data)?;
+    let src_validator = data.validator.clone();
+    data.validator = dst_validator.clone();
+    save_ls_data(deps.storage, &id, &data)?;
 
-    // get all nft
-    let query_tokens_msg = cw721_base::QueryMsg::<Empty>::Tokens {
-        owner: info.sender.clone().to_string(),
-        start_after: None,
-        limit: None,
+    let delegator_redelegate_msg = delegator::msg::ExecuteMsg::Redelegate {
+        dst_validator: dst_validator.clone(),
     };
-    let records: cw721::TokensResponse = deps
-        .querier
-        .query_wasm_smart(portal_env.cw721_address, &query_tokens_msg)?;
-    // get delegators
-    let delegators: Vec<String> = records
-        .tokens
-        .into_iter()
-        .map(|item| load_ls_data(deps.storage, item).unwrap().delegator_address)
-        .collect();
 
-    // send getting reward to delegetors
-    let mut res = Response::new();
-    for del in delegators.iter() {
-        let delegator_withdraw_reward_msg = delegator::msg::ExecuteMsg::WithdrawReward {
-            recipient: info.sender.clone().to_string(),
-        };
-        let delegator_wasm_exec_msg = WasmMsg::Execute {
-            contract_addr: del.clone(),
-            msg: to_json_binary(&delegator_withdraw_reward_msg)?,
+    Ok(Response::new()
+        .add_messages(harvest)
+        .add_message(WasmMsg::Execute {
+            contract_addr: data.delegator_address,
+            msg: to_json_binary(&delegator_redelegate_msg)?,
             funds: vec![],
+        })
+        .add_attribute("method", "execute")
+        .add_attribute("action", "redelegate")
+        .add_attribute("id", id)
+        .add_attribute("src_validator", src_validator)
+        .add_attribute("dst_validator", dst_validator))
+}
+
+/// Accepts exactly one coin of the bonded denom, at least `min_delegation` of it.
+fn validate_delegation_funds(
+    deps: Deps,
+    info: &MessageInfo,
+    min_delegation: Uint128,
+) -> Result<Coin, ContractError> {
+    let coin = match info.funds.as_slice() {
+        [] => return Err(ContractError::NoFunds {}),
+        [coin] => coin,
+        _ => return Err(ContractError::MultipleDenoms {}),
+    };
+
+    let bonded_denom = deps.querier.query_bonded_denom()?;
+    if coin.denom != bonded_denom {
+        return Err(ContractError::WrongDenom {
+            expected: bonded_denom,
+            got: coin.denom.clone(),
+        });
+    }
+    if coin.amount.is_zero() || coin.amount < min_delegation {
+        return Err(ContractError::BelowMinimum {
+            minimum: min_delegation,
+            got: coin.amount,
+        });
+    }
+
+    Ok(coin.clone())
+}
+
+/// Rejects validators that are unknown to the staking module or not in the active set,
+/// and, when the allow list is enabled, validators that are not listed.
+/// Returns the validator as the staking module reports it over gRPC.
+fn validate_validator(deps: Deps, validator: &str) -> Result<ValidatorInfo, ContractError> {
+    let invalid = || ContractError::InvalidValidator {
+        validator: validator.to_string(),
+    };
+
+    deps.querier
+        .query_validator(validator)?
+        .ok_or_else(invalid)?;
+    // the staking querier also answers for jailed and unbonding validators
+    let info = query_validator_info(deps.querier, validator)?;
+    if info.jailed || info.status != BOND_STATUS_BONDED {
+        return Err(invalid());
+    }
+
+    let allow_list_enabled = VALIDATOR_ALLOW_LIST_ENABLED
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    if allow_list_enabled && !VALIDATOR_ALLOW_LIST.has(deps.storage, validator) {
+        return Err(ContractError::ValidatorNotAllowed {
+            validator: validator.to_string(),
+        });
+    }
+
+    Ok(info)
+}
+
+fn execute_update_validator_allow_list(
+    deps: DepsMut,
+    info: MessageInfo,
+    enabled: bool,
+    add: Vec<String>,
+    remove: Vec<String>,
+) -> Result<Response, ContractError> {
+    cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+    VALIDATOR_ALLOW_LIST_ENABLED.save(deps.storage, &enabled)?;
+    for validator in add.iter() {
+        if deps.querier.query_validator(validator)?.is_none() {
+            return Err(ContractError::InvalidValidator {
+                validator: validator.to_string(),
+            });
+        }
+        VALIDATOR_ALLOW_LIST.save(deps.storage, validator, &Empty {})?;
+    }
+    for validator in remove.iter() {
+        VALIDATOR_ALLOW_LIST.remove(deps.storage, validator);
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "execute")
+        .add_attribute("action", "update_validator_allow_list")
+        .add_attribute("enabled", enabled.to_string()))
+}
+
+fn execute_update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    cw20_code_id: Option<u64>,
+    delegator_code_id: Option<u64>,
+    min_delegation: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+    let mut portal_env = PORTAL_ENV.load(deps.storage)?;
+    if let Some(cw20_code_id) = cw20_code_id {
+        portal_env.cw20_code_id = cw20_code_id;
+    }
+    if let Some(delegator_code_id) = delegator_code_id {
+        portal_env.delegator_code_id = delegator_code_id;
+    }
+    if let Some(min_delegation) = min_delegation {
+        portal_env.min_delegation = min_delegation;
+    }
+    PORTAL_ENV.save(deps.storage, &portal_env)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "execute")
+        .add_attribute("action", "update_config")
+        .add_attribute("cw20_code_id", portal_env.cw20_code_id.to_string())
+        .add_attribute(
+            "delegator_code_id",
+            portal_env.delegator_code_id.to_string(),
+        )
+        .add_attribute("min_delegation", portal_env.min_delegation))
+}
+
+fn execute_migrate_delegators(
+    deps: DepsMut,
+    info: MessageInfo,
+    mut ids: Vec<PositionId>,
+    new_code_id: u64,
+) -> Result<Response, ContractError> {
+    cw_ownable::assert_owner(deps.storage, &info.sender)?;
+    ids.sort();
+    ids.dedup();
+
+    // the portal is the wasm admin of every delegator it instantiated
+    let migrate_msg = to_json_binary(&delegator::msg::MigrateMsg {})?;
+    let mut res = Response::new()
+        .add_attribute("method", "execute")
+        .add_attribute("action", "migrate_delegators")
+        .add_attribute("new_code_id", new_code_id.to_string());
+    for id in ids {
+        let data = load_ls_data(deps.storage, &id)?;
+        res = res
+            .add_message(WasmMsg::Migrate {
+                contract_addr: data.delegator_address,
+                new_code_id,
+                msg: migrate_msg.clone(),
+            })
+            .add_attribute("id", id);
+    }
+
+    Ok(res)
+}
+
+fn execute_migrate_legacy_positions(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw20_code_id: u64,
+    delegator_code_id: u64,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    cw_ownable::assert_owner(deps.storage, &info.sender)?;
+    let cursor = v0_1::MIGRATION_CURSOR
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NothingToMigrate {})?;
+    let limit = limit.unwrap_or(MAX_LIMIT).min(MAX_LIMIT) as usize;
+
+    let start = cursor
+        .as_ref()
+        .map(|(validator, seq)| Bound::exclusive((validator.as_str(), *seq)));
+    let records = v0_1::LS_DATA
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let portal_env = PORTAL_ENV.load(deps.storage)?;
+    let bonded_denom = deps.querier.query_bonded_denom()?;
+
+    // the tokens of 0.1.x had no minter and no rewards, the portal is their wasm admin
+    let cw20_migrate_msg = to_json_binary(&cw20_base::msg::MigrateMsg {
+        reward_denom: Some(bonded_denom.clone()),
+        minter: Some(env.contract.address.to_string()),
+    })?;
+    // the delegators recover their validator from the delegation
+    let delegator_migrate_msg = to_json_binary(&delegator::msg::MigrateMsg {})?;
+
+    let mut res = Response::new()
+        .add_attribute("method", "execute")
+        .add_attribute("action", "migrate_legacy_positions");
+    for ((validator, seq), data) in records.iter() {
+        // created after the contract migration
+        if data.validator.is_some() {
+            continue;
+        }
+
+        let id = PositionId::new(validator.clone(), *seq);
+        let data = LiquidStakingData {
+            token_address: data.token_address.clone(),
+            delegator_address: data.delegator_address.clone(),
+            validator: validator.clone(),
+            created_at: 0,
         };
-        res = res.add_message(delegator_wasm_exec_msg)
+        // drop the legacy record first, the indexes cannot read it
+        v0_1::LS_DATA.remove(deps.storage, (validator, *seq));
+        positions().save(deps.storage, (validator, *seq), &data)?;
+
+        // the cw721 was migrated along with the portal, so the record can take its metadata
+        if record_exists(deps.as_ref(), &portal_env, &id)? {
+            let (delegated, _) = query_position_stake(deps.as_ref(), &data)?;
+            let record = legacy_record(
+                deps.as_ref(),
+                &id,
+                &data,
+                coin(delegated.u128(), &bonded_denom),
+            )?;
+            res = res.add_message(update_metadata_msg(&portal_env, &id, record)?);
+        }
+        res = res
+            .add_message(WasmMsg::Migrate {
+                contract_addr: data.token_address,
+                new_code_id: cw20_code_id,
+                msg: cw20_migrate_msg.clone(),
+            })
+            .add_message(WasmMsg::Migrate {
+                contract_addr: data.delegator_address,
+                new_code_id: delegator_code_id,
+                msg: delegator_migrate_msg.clone(),
+            })
+            .add_attribute("id", id);
     }
 
+    match records.last() {
+        Some(((validator, seq), _)) if records.len() == limit => {
+            v0_1::MIGRATION_CURSOR.save(deps.storage, &Some((validator.clone(), *seq)))?;
+            res = res.add_attribute("next_start_after", PositionId::new(validator.clone(), *seq));
+        }
+        _ => v0_1::MIGRATION_CURSOR.remove(deps.storage),
+    }
     Ok(res)
 }
 
-fn execute_undelegate(
+fn execute_update_fee_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    fee_bps: u16,
+    treasury: String,
+) -> Result<Response, ContractError> {
+    cw_ownable::assert_owner(deps.storage, &info.sender)?;
+    if fee_bps > MAX_FEE_BPS {
+        return Err(ContractError::FeeTooHigh {
+            fee_bps,
+            max_fee_bps: MAX_FEE_BPS,
+        });
+    }
+
+    let treasury = deps.api.addr_validate(&treasury)?;
+    FEE_CONFIG.save(
+        deps.storage,
+        &FeeConfig {
+            fee_bps,
+            treasury: treasury.clone(),
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("method", "execute")
+        .add_attribute("action", "update_fee_config")
+        .add_attribute("fee_bps", fee_bps.to_string())
+        .add_attribute("treasury", treasury))
+}
+
+fn execute_update_token_metadata(
+    deps: DepsMut,
+    info: MessageInfo,
+    metadata: TokenMetadata,
+) -> Result<Response, ContractError> {
+    cw_ownable::assert_owner(deps.storage, &info.sender)?;
+    TOKEN_METADATA.save(deps.storage, &metadata)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "execute")
+        .add_attribute("action", "update_token_metadata")
+        .add_attribute("name_template", metadata.name_template)
+        .add_attribute("symbol_template", metadata.symbol_template))
+}
+
+fn execute_withdraw_all_reward(
+    deps: DepsMut,
+    info: MessageInfo,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let portal_env = PORTAL_ENV.load(deps.storage)?;
+    let max_records = limit
+        .unwrap_or(WITHDRAW_ALL_REWARD_MAX_RECORDS)
+        .min(WITHDRAW_ALL_REWARD_MAX_RECORDS);
+
+    // get nft page by page, up to max_records
+    let mut records: Vec<String> = vec![];
+    let mut cursor = start_after;
+    let mut exhausted = false;
+    while (records.len() as u32) < max_records {
+        let page_limit = (max_records - records.len() as u32).min(WITHDRAW_ALL_REWARD_PAGE_LIMIT);
+        let query_tokens_msg = cw721_base::QueryMsg::<Empty>::Tokens {
+            owner: info.sender.to_string(),
+            start_after: cursor.clone(),
+            limit: Some(page_limit),
+        };
+        let page: cw721::TokensResponse = deps
+            .querier
+            .query_wasm_smart(&portal_env.cw721_address, &query_tokens_msg)?;
+
+        let page_len = page.tokens.len() as u32;
+        if let Some(last) = page.tokens.last() {
+            cursor = Some(last.clone());
+        }
+        records.extend(page.tokens);
+        if page_len < page_limit {
+            exhausted = true;
+            break;
+        }
+    }
+
+    // get positions
+    let positions = records
+        .iter()
+        .map(|token_id| {
+            let id: PositionId = token_id.parse()?;
+            Ok(load_ls_data(deps.storage, &id)?)
+        })
+        .collect::<Result<Vec<_>, ContractError>>()?;
+
+    // send getting reward to delegetors, drained positions have nothing to withdraw
+    let mut res = Response::new();
+    for data in positions.iter() {
+        res = res.add_messages(harvest_msg(deps.as_ref(), data)?)
+    }
+
+    res = res
+        .add_attribute("method", "execute")
+        .add_attribute("action", "withdraw_all_reward")
+        .add_attribute("records", positions.len().to_string());
+    // let clients continue from where this call stopped
+    if let (false, Some(next)) = (exhausted, cursor) {
+        res = res.add_attribute("next_start_after", next);
+    }
+    Ok(res)
+}
+
+fn execute_withdraw_rewards(
+    deps: DepsMut,
+    info: MessageInfo,
+    mut ids: Vec<PositionId>,
+) -> Result<Response, ContractError> {
+    let portal_env = PORTAL_ENV.load(deps.storage)?;
+    ids.sort();
+    ids.dedup();
+
+    // rewards go to whoever holds the tokens when they are harvested, so only the record
+    // owner may harvest, not someone who just bought tokens to collect what they missed.
+    // Drained positions are skipped, their delegator has no delegation to withdraw from.
+    let mut res = Response::new();
+    for id in ids {
+        assert_record_owner(deps.as_ref(), &portal_env, &id, &info.sender)?;
+        let data = load_ls_data(deps.storage, &id)?;
+        res = res
+            .add_messages(harvest_msg(deps.as_ref(), &data)?)
+            .add_attribute("id", id);
+    }
+
+    Ok(res
+        .add_attribute("method", "execute")
+        .add_attribute("action", "withdraw_rewards"))
+}
+
+fn execute_compound(
+    deps: DepsMut,
+    info: MessageInfo,
+    id: PositionId,
+) -> Result<Response, ContractError> {
+    let portal_env = PORTAL_ENV.load(deps.storage)?;
+    assert_record_owner(deps.as_ref(), &portal_env, &id, &info.sender)?;
+    let data = load_ls_data(deps.storage, &id)?;
+
+    // rewards are restaked instead of paid out, so each LSCT is backed by more stake;
+    // those in other denoms cannot be and go to the caller
+    let delegator_compound_msg = delegator::msg::ExecuteMsg::Compound {
+        recipient: info.sender.to_string(),
+        fee: reward_fee(deps.storage)?,
+    };
+
+    Ok(Response::new()
+        .add_message(WasmMsg::Execute {
+            contract_addr: data.delegator_address,
+            msg: to_json_binary(&delegator_compound_msg)?,
+            funds: vec![],
+        })
+        .add_attribute("method", "execute")
+        .add_attribute("action", "compound")
+        .add_attribute("id", id))
+}
+
+/// Withdraws the rewards of a position to its token, which shares them among the holders.
+fn withdraw_reward_msg(store: &dyn Storage, data: &LiquidStakingData) -> StdResult<WasmMsg> {
+    let delegator_withdraw_reward_msg = delegator::msg::ExecuteMsg::WithdrawReward {
+        recipient: data.token_address.clone(),
+        fee: reward_fee(store)?,
+    };
+    Ok(WasmMsg::Execute {
+        contract_addr: data.delegator_address.clone(),
+        msg: to_json_binary(&delegator_withdraw_reward_msg)?,
+        funds: vec![],
+    })
+}
+
+/// Protocol fee the delegators take out of the rewards, if any
+fn reward_fee(store: &dyn Storage) -> StdResult<Option<delegator::msg::Fee>> {
+    Ok(FEE_CONFIG
+        .may_load(store)?
+        .filter(|config| config.fee_bps > 0)
+        .map(|config| delegator::msg::Fee {
+            bps: config.fee_bps,
+            recipient: config.treasury.to_string(),
+        }))
+}
+
+/// Stake actually delegated by a position and the LSCT supply backed by it.
+fn query_position_stake(deps: Deps, data: &LiquidStakingData) -> StdResult<(Uint128, Uint128)> {
+    let delegated = deps
+        .querier
+        .query_delegation(&data.delegator_address, &data.validator)?
+        .map(|delegation| delegation.amount.amount)
+        .unwrap_or_default();
+    let token_info: cw20::TokenInfoResponse = deps
+        .querier
+        .query_wasm_smart(&data.token_address, &cw20_base::msg::QueryMsg::TokenInfo {})?;
+    Ok((delegated, token_info.total_supply))
+}
+
+/// Withdraws pending rewards ahead of a change to the stake or the supply, so they go to
+/// the current holders instead of being withdrawn to the delegator along with it.
+fn harvest_msg(deps: Deps, data: &LiquidStakingData) -> StdResult<Option<WasmMsg>> {
+    let has_rewards = deps
+        .querier
+        .query_delegation(&data.delegator_address, &data.validator)?
+        .map_or(false, |delegation| {
+            delegation
+                .accumulated_rewards
+                .iter()
+                .any(|coin| !coin.amount.is_zero())
+        });
+    if !has_rewards {
+        return Ok(None);
+    }
+    withdraw_reward_msg(deps.storage, data).map(Some)
+}
+
+fn execute_receive(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    id: String,
+    wrapper: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    // only the tokens of a position can be sent here
+    let (pk, _) = positions()
+        .idx
+        .token
+        .item(deps.storage, info.sender.to_string())?
+        .ok_or_else(|| ContractError::UnknownPositionToken {
+            address: info.sender.to_string(),
+        })?;
+    let id = position_id_from_pk(pk)?;
+    let sender = deps.api.addr_validate(&wrapper.sender)?;
+
+    match from_json(&wrapper.msg)? {
+        ReceiveMsg::Undelegate {} => undelegate(deps, env, sender, id, wrapper.amount, true),
+    }
+}
+
+/// Burns `amount` LSCT of `owner` and undelegates their share of the stake for them.
+/// `received` is set when the tokens were sent to the portal, which then burns its own.
+fn undelegate(
+    mut deps: DepsMut,
+    env: Env,
+    owner: Addr,
+    id: PositionId,
     amount: Uint128,
+    received: bool,
 ) -> Result<Response, ContractError> {
-    let data = load_ls_data(deps.storage, id.clone())?;
+    let data = load_ls_data(deps.storage, &id)?;
+
+    // LSCT redeem their share of the actual delegation, which a slash leaves short of the supply
+    let (delegated, supply) = query_position_stake(deps.as_ref(), &data)?;
+    let stake = if supply.is_zero() {
+        Uint128::zero()
+    } else {
+        amount.multiply_ratio(delegated, supply)
+    };
+    if stake.is_zero() {
+        return Err(ContractError::NothingToUndelegate { amount });
+    }
+
+    let portal_env = PORTAL_ENV.load(deps.storage)?;
+    let bonded_denom = deps.querier.query_bonded_denom()?;
+    let update_record = update_record_msg(
+        deps.as_ref(),
+        &portal_env,
+        &id,
+        &data,
+        coin(delegated.saturating_sub(stake).u128(), bonded_denom),
+    )?;
+    let (seq, completion_time) = record_unbonding(deps.branch(), &env, &owner, id, &data, stake)?;
 
     // burn lst
-    let cw20_exec_burn_from_msg = cw20_base::msg::ExecuteMsg::BurnFrom {
-        owner: info.sender.to_string(),
-        amount: amount.clone(),
+    let cw20_burn_msg = if received {
+        cw20_base::msg::ExecuteMsg::Burn { amount }
+    } else {
+        cw20_base::msg::ExecuteMsg::BurnFrom {
+            owner: owner.to_string(),
+            amount,
+        }
     };
 
     let delegator_undelegate_msg = delegator::msg::ExecuteMsg::Undelegate {
-        amount: amount.clone(),
+        id: seq,
+        amount: stake,
+        completion_time,
     };
 
-    Ok(Response::new()
+    // when the portal holds the tokens, it is credited their share of the harvest,
+    // which the reply to the burn forwards
+    let harvest = harvest_msg(deps.as_ref(), &data)?;
+    let forward_rewards = received && harvest.is_some();
+    let burn_msg = WasmMsg::Execute {
+        contract_addr: data.token_address.clone(),
+        msg: to_json_binary(&cw20_burn_msg)?,
+        funds: vec![],
+    };
+    let mut res = Response::new().add_messages(harvest);
+    if forward_rewards {
+        PENDING_FORWARD.save(
+            deps.storage,
+            &PendingForward {
+                token_address: data.token_address.clone(),
+                recipient: owner.clone(),
+            },
+        )?;
+        res = res.add_submessage(SubMsg::reply_on_success(
+            burn_msg,
+            EXEC_FORWARD_REWARDS_CALLBACK_ID,
+        ));
+    } else {
+        res = res.add_message(burn_msg);
+    }
+
+    Ok(res
         .add_message(WasmMsg::Execute {
-            contract_addr: data.token_address,
-            msg: to_json_binary(&cw20_exec_burn_from_msg)?,
+            contract_addr: data.delegator_address,
+            msg: to_json_binary(&delegator_undelegate_msg)?,
             funds: vec![],
         })
+        .add_messages(update_record)
+        .add_attribute("method", "execute")
+        .add_attribute("action", "undelegate")
+        .add_attribute("amount", amount)
+        .add_attribute("stake", stake)
+        .add_attribute("completion_time", completion_time.to_string()))
+}
+
+/// Keeps the record of `id` showing `delegated`, `None` when its owner burned it through
+/// the cw721. Records still without metadata are rebuilt from the position.
+fn update_record_msg(
+    deps: Deps,
+    portal_env: &PortalEnv,
+    id: &PositionId,
+    data: &LiquidStakingData,
+    delegated: Coin,
+) -> StdResult<Option<WasmMsg>> {
+    // a missing record must not keep the holders from undelegating
+    if !record_exists(deps, portal_env, id)? {
+        return Ok(None);
+    }
+    let info: cw721::NftInfoResponse<cw721_base::Extension> = deps.querier.query_wasm_smart(
+        &portal_env.cw721_address,
+        &cw721_base::QueryMsg::<Empty>::NftInfo {
+            token_id: id.to_string(),
+        },
+    )?;
+    let record = match info.extension {
+        Some(record) if !record.validator.is_empty() => cw721_base::Metadata {
+            delegated,
+            ..record
+        },
+        _ => legacy_record(deps, id, data, delegated)?,
+    };
+    update_metadata_msg(portal_env, id, record).map(Some)
+}
+
+/// Reads the record of `id` from the cw721 storage, so a missing one is told apart from a
+/// failing query.
+fn record_exists(deps: Deps, portal_env: &PortalEnv, id: &PositionId) -> StdResult<bool> {
+    let token_id = id.to_string();
+    let tokens =
+        cw721_base::Cw721Contract::<cw721_base::Extension, Empty, Empty, Empty>::default().tokens;
+    let record = deps
+        .querier
+        .query_wasm_raw(&portal_env.cw721_address, tokens.key(&token_id).to_vec())?;
+    Ok(record.is_some())
+}
+
+/// Metadata of a record minted by 0.1.x, which had none. The initial amount and creation
+/// time of those positions are unknown and left empty.
+fn legacy_record(
+    deps: Deps,
+    id: &PositionId,
+    data: &LiquidStakingData,
+    delegated: Coin,
+) -> StdResult<cw721_base::Metadata> {
+    let token_info: cw20::TokenInfoResponse = deps
+        .querier
+        .query_wasm_smart(&data.token_address, &cw20_base::msg::QueryMsg::TokenInfo {})?;
+    Ok(cw721_base::Metadata {
+        name: token_info.name,
+        validator: id.validator.clone(),
+        initial_amount: coin(0, &delegated.denom),
+        delegated,
+        token_address: data.token_address.clone(),
+        delegator_address: data.delegator_address.clone(),
+        created_height: data.created_at,
+        ..cw721_base::Metadata::default()
+    })
+}
+
+fn update_metadata_msg(
+    portal_env: &PortalEnv,
+    id: &PositionId,
+    record: cw721_base::Metadata,
+) -> StdResult<WasmMsg> {
+    let update_msg =
+        cw721_base::ExecuteMsg::<cw721_base::Extension, cw721_base::ExtensionMsg>::Extension {
+            msg: cw721_base::ExtensionMsg::UpdateMetadata {
+                token_id: id.to_string(),
+                token_uri: Some(record_token_uri(&record)?),
+                extension: Some(record),
+            },
+        };
+    Ok(WasmMsg::Execute {
+        contract_addr: portal_env.cw721_address.clone(),
+        msg: to_json_binary(&update_msg)?,
+        funds: vec![],
+    })
+}
+
+/// Records an unbonding of `stake` for `owner`, so it can be claimed once the chain releases it.
+/// Returns its sequence, which also identifies it on the delegator, and its completion time.
+fn record_unbonding(
+    deps: DepsMut,
+    env: &Env,
+    owner: &Addr,
+    id: PositionId,
+    data: &LiquidStakingData,
+    stake: Uint128,
+) -> StdResult<(u64, Timestamp)> {
+    let unbonding_time = query_unbonding_time(deps.querier)?;
+    let seq = UNBONDING_SEQ.may_load(deps.storage)?.unwrap_or_default();
+    UNBONDING_SEQ.save(deps.storage, &(seq + 1))?;
+    let completion_time = env.block.time.plus_seconds(unbonding_time);
+    UNBONDINGS.save(
+        deps.storage,
+        (owner, seq),
+        &Unbonding {
+            id,
+            delegator_address: data.delegator_address.clone(),
+            amount: stake,
+            completion_time,
+        },
+    )?;
+    Ok((seq, completion_time))
+}
+
+fn execute_receive_nft(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    wrapper: Cw721ReceiveMsg,
+) -> Result<Response, ContractError> {
+    // only records can be sent here
+    let portal_env = PORTAL_ENV.load(deps.storage)?;
+    if info.sender != portal_env.cw721_address {
+        return Err(ContractError::Unauthorized {});
+    }
+    let id: PositionId = wrapper.token_id.parse()?;
+    let sender = deps.api.addr_validate(&wrapper.sender)?;
+
+    match from_json(&wrapper.msg)? {
+        NftReceiveMsg::Close {} => close_position(deps, env, portal_env, sender, id),
+    }
+}
+
+/// Closes a position whose record `owner` sent to the portal: burns the whole LSCT supply,
+/// which `owner` must hold and allow the portal to burn, undelegates the whole stake for
+/// them and burns the record.
+fn close_position(
+    mut deps: DepsMut,
+    env: Env,
+    portal_env: PortalEnv,
+    owner: Addr,
+    id: PositionId,
+) -> Result<Response, ContractError> {
+    let data = load_ls_data(deps.storage, &id)?;
+    let (delegated, supply) = query_position_stake(deps.as_ref(), &data)?;
+    let balance: cw20::BalanceResponse = deps.querier.query_wasm_smart(
+        &data.token_address,
+        &cw20_base::msg::QueryMsg::Balance {
+            address: owner.to_string(),
+        },
+    )?;
+    if balance.balance < supply {
+        return Err(ContractError::PositionNotFullyOwned {
+            balance: balance.balance,
+            supply,
+        });
+    }
+    CLOSED_POSITIONS.save(deps.storage, id.key(), &env.block.height)?;
+
+    // the owner holds every token, so the harvest is all theirs to claim from the token
+    let mut res = Response::new().add_messages(harvest_msg(deps.as_ref(), &data)?);
+    if !supply.is_zero() {
+        let cw20_burn_msg = cw20_base::msg::ExecuteMsg::BurnFrom {
+            owner: owner.to_string(),
+            amount: supply,
+        };
+        res = res.add_message(WasmMsg::Execute {
+            contract_addr: data.token_address.clone(),
+            msg: to_json_binary(&cw20_burn_msg)?,
+            funds: vec![],
+        });
+    }
+    if !delegated.is_zero() {
+        let (seq, completion_time) =
+            record_unbonding(deps.branch(), &env, &owner, id.clone(), &data, delegated)?;
+        let delegator_undelegate_msg = delegator::msg::ExecuteMsg::Undelegate {
+            id: seq,
+            amount: delegated,
+            completion_time,
+        };
+        res = res
+            .add_message(WasmMsg::Execute {
+                contract_addr: data.delegator_address.clone(),
+                msg: to_json_binary(&delegator_undelegate_msg)?,
+                funds: vec![],
+            })
+            .add_attribute("completion_time", completion_time.to_string());
+    }
+
+    let cw721_burn_msg =
+        cw721_base::ExecuteMsg::<cw721_base::Extension, cw721_base::ExtensionMsg>::Burn {
+            token_id: id.to_string(),
+        };
+    Ok(res
         .add_message(WasmMsg::Execute {
-            contract_addr: data.delegator_address,
-            msg: to_json_binary(&delegator_undelegate_msg)?,
+            contract_addr: portal_env.cw721_address,
+            msg: to_json_binary(&cw721_burn_msg)?,
             funds: vec![],
-        }))
+        })
+        .add_attribute("method", "execute")
+        .add_attribute("action", "close")
+        .add_attribute("id", id)
+        .add_attribute("burned", supply)
+        .add_attribute("stake", delegated))
+}
+
+fn execute_claim(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let matured: Vec<(u64, Unbonding)> = UNBONDINGS
+        .prefix(&info.sender)
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|item| {
+            item.as_ref().map_or(true, |(_, unbonding)| {
+                unbonding.completion_time <= env.block.time
+            })
+        })
+        .collect::<StdResult<_>>()?;
+    let mut matured_by_delegator: BTreeMap<String, Vec<u64>> = BTreeMap::new();
+    for (seq, unbonding) in matured {
+        matured_by_delegator
+            .entry(unbonding.delegator_address)
+            .or_default()
+            .push(seq);
+    }
+
+    // the chain releases an unbonding at the end of the block reaching its completion time,
+    // and slashes lower what it pays, so only what the delegators report released is claimed
+    let mut res = Response::new();
+    let mut claimed = Uint128::zero();
+    for (delegator_address, seqs) in matured_by_delegator {
+        let unbondings: delegator::msg::UnbondingsResponse = deps
+            .querier
+            .query_wasm_smart(&delegator_address, &delegator::msg::QueryMsg::Unbondings {})?;
+        let mut ids = vec![];
+        for unbonding in unbondings.unbondings {
+            if unbonding.released && seqs.contains(&unbonding.id) {
+                UNBONDINGS.remove(deps.storage, (&info.sender, unbonding.id));
+                claimed += unbonding.amount;
+                ids.push(unbonding.id);
+            }
+        }
+        if ids.is_empty() {
+            continue;
+        }
+
+        let delegator_claim_msg = delegator::msg::ExecuteMsg::Claim {
+            recipient: info.sender.to_string(),
+            ids,
+        };
+        res = res.add_message(WasmMsg::Execute {
+            contract_addr: delegator_address,
+            msg: to_json_binary(&delegator_claim_msg)?,
+            funds: vec![],
+        });
+    }
+    if res.messages.is_empty() {
+        return Err(ContractError::NothingToClaim {});
+    }
+
+    Ok(res
+        .add_attribute("method", "execute")
+        .add_attribute("action", "claim")
+        .add_attribute("amount", claimed))
 }
 
 /// Handling contract query
@@ -304,7 +1313,27 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Env {} => to_json_binary(&query_env(deps)?),
         QueryMsg::Data { id } => to_json_binary(&query_data(deps, id)?),
-        QueryMsg::All {} => to_json_binary(&query_all(deps)?),
+        QueryMsg::All { start_after, limit } => {
+            to_json_binary(&query_all(deps, start_after, limit)?)
+        }
+        QueryMsg::ByValidator {
+            validator,
+            start_after,
+            limit,
+        } => to_json_binary(&query_by_validator(deps, validator, start_after, limit)?),
+        QueryMsg::Position { id } => to_json_binary(&query_position(deps, id)?),
+        QueryMsg::PositionByToken { address } => {
+            to_json_binary(&query_position_by_token(deps, address)?)
+        }
+        QueryMsg::PositionByDelegator { address } => {
+            to_json_binary(&query_position_by_delegator(deps, address)?)
+        }
+        QueryMsg::ValidatorAllowList {} => to_json_binary(&query_validator_allow_list(deps)?),
+        QueryMsg::Claims { address } => to_json_binary(&query_claims(deps, address)?),
+        QueryMsg::ExchangeRate { id } => to_json_binary(&query_exchange_rate(deps, id)?),
+        QueryMsg::FeeConfig {} => to_json_binary(&query_fee_config(deps)?),
+        QueryMsg::TokenMetadata {} => to_json_binary(&query_token_metadata(deps)?),
+        QueryMsg::Ownership {} => to_json_binary(&cw_ownable::get_ownership(deps.storage)?),
     }
 }
 
@@ -314,40 +1343,300 @@ fn query_env(deps: Deps) -> StdResult<EnvResponse> {
         cw20_code_id: portal_env.cw20_code_id,
         cw721_address: portal_env.cw721_address,
         delegator_code_id: portal_env.delegator_code_id,
+        min_delegation: portal_env.min_delegation,
     })
 }
 
-fn query_data(deps: Deps, id: String) -> StdResult<DataResponse> {
-    let data = load_ls_data(deps.storage, id)?;
+fn query_fee_config(deps: Deps) -> StdResult<FeeConfigResponse> {
+    let config = FEE_CONFIG.may_load(deps.storage)?;
+    Ok(FeeConfigResponse {
+        fee_bps: config.as_ref().map_or(0, |config| config.fee_bps),
+        treasury: config.map(|config| config.treasury.to_string()),
+        max_fee_bps: MAX_FEE_BPS,
+    })
+}
+
+fn query_token_metadata(deps: Deps) -> StdResult<TokenMetadataResponse> {
+    let metadata = TOKEN_METADATA.may_load(deps.storage)?.unwrap_or_default();
+    Ok(TokenMetadataResponse {
+        name_template: metadata.name_template,
+        symbol_template: metadata.symbol_template,
+        description_template: metadata.description_template,
+        logo_url: metadata.logo_url,
+    })
+}
+
+fn query_exchange_rate(deps: Deps, id: PositionId) -> StdResult<ExchangeRateResponse> {
+    let data = load_ls_data(deps.storage, &id)?;
+    let (delegated, supply) = query_position_stake(deps, &data)?;
+    let rate = if supply.is_zero() {
+        Decimal::one()
+    } else {
+        Decimal::from_ratio(delegated, supply)
+    };
+    Ok(ExchangeRateResponse {
+        delegated,
+        supply,
+        rate,
+    })
+}
+
+fn query_data(deps: Deps, id: PositionId) -> StdResult<DataResponse> {
+    let data = load_ls_data(deps.storage, &id)?;
     Ok(DataResponse {
         token_address: data.token_address,
         delegator_address: data.delegator_address,
+        validator: data.validator,
     })
 }
 
-fn query_all(deps: Deps) -> StdResult<AllResponse> {
-    let all: StdResult<Vec<String>> = LS_DATA
-        .keys(deps.storage, None, None, Order::Ascending)
-        .map(|item| item.map(|(prefix, id)| prefix + "/" + &id.to_string()))
+fn query_position(deps: Deps, id: PositionId) -> StdResult<PositionResponse> {
+    let portal_env = PORTAL_ENV.load(deps.storage)?;
+    let data = load_ls_data(deps.storage, &id)?;
+
+    // the record of a closed position is burned
+    let closed_at = CLOSED_POSITIONS.may_load(deps.storage, id.key())?;
+    let owner = match closed_at {
+        Some(_) => None,
+        None => {
+            let owner: cw721::OwnerOfResponse = deps.querier.query_wasm_smart(
+                &portal_env.cw721_address,
+                &cw721_base::QueryMsg::<Empty>::OwnerOf {
+                    token_id: id.to_string(),
+                    include_expired: None,
+                },
+            )?;
+            Some(owner.owner)
+        }
+    };
+    let (delegated, supply) = query_position_stake(deps, &data)?;
+    let pending_rewards = deps
+        .querier
+        .query_delegation(&data.delegator_address, &data.validator)?
+        .map(|delegation| delegation.accumulated_rewards)
+        .unwrap_or_default();
+
+    Ok(PositionResponse {
+        id,
+        validator: data.validator,
+        owner,
+        token_address: data.token_address,
+        delegator_address: data.delegator_address,
+        delegated,
+        pending_rewards,
+        supply,
+        created_at: data.created_at,
+        closed_at,
+    })
+}
+
+fn query_position_by_token(deps: Deps, address: String) -> StdResult<PositionResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let (pk, _) = positions()
+        .idx
+        .token
+        .item(deps.storage, address.to_string())?
+        .ok_or_else(|| StdError::not_found("position"))?;
+    query_position(deps, position_id_from_pk(pk)?)
+}
+
+fn query_position_by_delegator(deps: Deps, address: String) -> StdResult<PositionResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let (pk, _) = positions()
+        .idx
+        .delegator
+        .item(deps.storage, address.to_string())?
+        .ok_or_else(|| StdError::not_found("position"))?;
+    query_position(deps, position_id_from_pk(pk)?)
+}
+
+/// Decodes the raw primary key returned by the unique indexes
+fn position_id_from_pk(pk: Vec<u8>) -> StdResult<PositionId> {
+    let (validator, seq) = <(&str, u32)>::from_vec(pk)?;
+    Ok(PositionId::new(validator, seq))
+}
+
+fn query_all(
+    deps: Deps,
+    start_after: Option<PositionId>,
+    limit: Option<u32>,
+) -> StdResult<AllResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_ref().map(|id| Bound::exclusive(id.key()));
+
+    let all: StdResult<Vec<PositionId>> = positions()
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(validator, seq)| PositionId::new(validator, seq)))
         .collect();
     Ok(AllResponse { data: all? })
 }
 
+/// Positions currently delegated to `validator`, in id order
+fn query_by_validator(
+    deps: Deps,
+    validator: String,
+    start_after: Option<PositionId>,
+    limit: Option<u32>,
+) -> StdResult<AllResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_ref().map(|id| Bound::exclusive(id.key()));
+
+    let all: StdResult<Vec<PositionId>> = positions()
+        .idx
+        .validator
+        .prefix(validator)
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(validator, seq)| PositionId::new(validator, seq)))
+        .collect();
+    Ok(AllResponse { data: all? })
+}
+
+fn query_validator_allow_list(deps: Deps) -> StdResult<ValidatorAllowListResponse> {
+    let enabled = VALIDATOR_ALLOW_LIST_ENABLED
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    let validators: StdResult<Vec<String>> = VALIDATOR_ALLOW_LIST
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect();
+    Ok(ValidatorAllowListResponse {
+        enabled,
+        validators: validators?,
+    })
+}
+
+fn query_claims(deps: Deps, address: String) -> StdResult<ClaimsResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let claims: StdResult<Vec<ClaimInfo>> = UNBONDINGS
+        .prefix(&address)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            item.map(|(_, unbonding)| ClaimInfo {
+                id: unbonding.id,
+                delegator_address: unbonding.delegator_address,
+                amount: unbonding.amount,
+                completion_time: unbonding.completion_time,
+            })
+        })
+        .collect();
+    Ok(ClaimsResponse { claims: claims? })
+}
+
 /// Handling submessage reply.
 /// For more info on submessage and reply, see https://github.com/CosmWasm/cosmwasm/blob/main/SEMANTICS.md#submessages
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn reply(_deps: DepsMut, _env: Env, _msg: Reply) -> Result<Response, ContractError> {
-    // With `Response` type, it is still possible to dispatch message to invoke external logic.
-    // See: https://github.com/CosmWasm/cosmwasm/blob/main/SEMANTICS.md#dispatching-messages
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        EXEC_DELEGATE_AND_TOKENIZE_CALLBACK_ID_1 => {
+            let pending = PENDING_LS_DATA.load(deps.storage)?;
+            let res = msg.result.into_result().map_err(StdError::generic_err)?;
+            assert_instantiated_address(&res, &pending.delegator_address)?;
 
-    todo!()
+            Ok(Response::new()
+                .add_attribute("method", "reply")
+                .add_attribute("delegator_address", pending.delegator_address))
+        }
+        EXEC_DELEGATE_AND_TOKENIZE_CALLBACK_ID_2 => {
+            let pending = PENDING_LS_DATA.load(deps.storage)?;
+            let res = msg.result.into_result().map_err(StdError::generic_err)?;
+            assert_instantiated_address(&res, &pending.token_address)?;
+            PENDING_LS_DATA.remove(deps.storage);
+
+            Ok(Response::new()
+                .add_attribute("method", "reply")
+                .add_attribute("token_address", pending.token_address))
+        }
+        EXEC_FORWARD_REWARDS_CALLBACK_ID => {
+            let pending = PENDING_FORWARD.load(deps.storage)?;
+            PENDING_FORWARD.remove(deps.storage);
+            let rewards: cw20_base::msg::PendingRewardsResponse = deps.querier.query_wasm_smart(
+                &pending.token_address,
+                &cw20_base::msg::QueryMsg::PendingRewards {
+                    address: env.contract.address.to_string(),
+                },
+            )?;
+
+            // a share too small to claim leaves nothing to forward
+            let mut res = Response::new();
+            if !rewards.rewards.amount.is_zero() {
+                let claim_msg = cw20_base::msg::ExecuteMsg::ClaimRewards {
+                    recipient: Some(pending.recipient.to_string()),
+                };
+                res = res.add_message(WasmMsg::Execute {
+                    contract_addr: pending.token_address,
+                    msg: to_json_binary(&claim_msg)?,
+                    funds: vec![],
+                });
+            }
+            Ok(res
+                .add_attribute("method", "reply")
+                .add_attribute("forwarded_rewards", rewards.rewards.amount))
+        }
+        id => Err(ContractError::UnknownReplyId { id }),
+    }
+}
+
+/// Checks the address reported by an instantiate2 reply against the predicted one.
+/// The address is read from the `MsgInstantiateContract2Response` data, falling back
+/// to the `_contract_address` attribute of the `instantiate` event.
+fn assert_instantiated_address(res: &SubMsgResponse, expected: &str) -> Result<(), ContractError> {
+    let from_data = res
+        .data
+        .as_ref()
+        .and_then(|data| MsgInstantiateContract2Response::decode(data.as_slice()).ok())
+        .map(|data| data.address)
+        .filter(|address| !address.is_empty());
+    let from_events = || {
+        res.events
+            .iter()
+            .filter(|event| event.ty == "instantiate")
+            .flat_map(|event| event.attributes.iter())
+            .find(|attr| attr.key == "_contract_address")
+            .map(|attr| attr.value.clone())
+    };
+    let actual = from_data
+        .or_else(from_events)
+        .ok_or(ContractError::NoInstantiatedAddress {})?;
+
+    if actual != expected {
+        return Err(ContractError::InstantiatedAddressMismatch {
+            expected: expected.to_string(),
+            actual,
+        });
+    }
+    Ok(())
 }
 
-fn load_ls_data(store: &dyn Storage, id: String) -> StdResult<LiquidStakingData> {
-    let splited: Vec<&str> = id.split('/').collect();
-    let prefix = splited[0];
-    let id: u32 = splited[1].trim().parse().unwrap();
-    LS_DATA.load(store, (prefix, id))
+fn assert_record_owner(
+    deps: Deps,
+    portal_env: &PortalEnv,
+    id: &PositionId,
+    sender: &Addr,
+) -> Result<(), ContractError> {
+    let owner: cw721::OwnerOfResponse = deps.querier.query_wasm_smart(
+        &portal_env.cw721_address,
+        &cw721_base::QueryMsg::<Empty>::OwnerOf {
+            token_id: id.to_string(),
+            include_expired: None,
+        },
+    )?;
+    if owner.owner != *sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+fn load_ls_data(store: &dyn Storage, id: &PositionId) -> StdResult<LiquidStakingData> {
+    positions().load(store, id.key())
+}
+
+fn save_ls_data(
+    store: &mut dyn Storage,
+    id: &PositionId,
+    data: &LiquidStakingData,
+) -> StdResult<()> {
+    positions().save(store, id.key(), data)
 }
 
 fn instantiate_address(
@@ -366,3 +1655,150 @@ fn instantiate_address(
     };
     api.addr_humanize(&_address?)
 }
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::{Event, SubMsgResult};
+
+    use super::*;
+
+    fn pending() -> LiquidStakingData {
+        LiquidStakingData {
+            token_address: "token".to_string(),
+            delegator_address: "delegator".to_string(),
+            validator: "validator".to_string(),
+            created_at: 12_345,
+        }
+    }
+
+    fn instantiate_reply(id: u64, address: &str) -> Reply {
+        Reply {
+            id,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![Event::new("instantiate").add_attribute("_contract_address", address)],
+                data: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn reply_accepts_predicted_addresses() {
+        let mut deps = mock_dependencies();
+        PENDING_LS_DATA
+            .save(deps.as_mut().storage, &pending())
+            .unwrap();
+
+        reply(
+            deps.as_mut(),
+            mock_env(),
+            instantiate_reply(EXEC_DELEGATE_AND_TOKENIZE_CALLBACK_ID_1, "delegator"),
+        )
+        .unwrap();
+        reply(
+            deps.as_mut(),
+            mock_env(),
+            instantiate_reply(EXEC_DELEGATE_AND_TOKENIZE_CALLBACK_ID_2, "token"),
+        )
+        .unwrap();
+
+        assert!(PENDING_LS_DATA
+            .may_load(deps.as_ref().storage)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn reply_reads_address_from_data() {
+        let mut deps = mock_dependencies();
+        PENDING_LS_DATA
+            .save(deps.as_mut().storage, &pending())
+            .unwrap();
+
+        let data = MsgInstantiateContract2Response {
+            address: "delegator".to_string(),
+            data: vec![],
+        };
+        let msg = Reply {
+            id: EXEC_DELEGATE_AND_TOKENIZE_CALLBACK_ID_1,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(data.encode_to_vec().into()),
+            }),
+        };
+        reply(deps.as_mut(), mock_env(), msg).unwrap();
+    }
+
+    #[test]
+    fn reply_rejects_unexpected_address() {
+        let mut deps = mock_dependencies();
+        PENDING_LS_DATA
+            .save(deps.as_mut().storage, &pending())
+            .unwrap();
+
+        let err = reply(
+            deps.as_mut(),
+            mock_env(),
+            instantiate_reply(EXEC_DELEGATE_AND_TOKENIZE_CALLBACK_ID_2, "other"),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::InstantiatedAddressMismatch { expected, actual }
+                if expected == "token" && actual == "other"
+        ));
+
+        let err = reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: EXEC_DELEGATE_AND_TOKENIZE_CALLBACK_ID_1,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: None,
+                }),
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::NoInstantiatedAddress {}));
+
+        let err = reply(deps.as_mut(), mock_env(), instantiate_reply(99, "token")).unwrap_err();
+        assert!(matches!(err, ContractError::UnknownReplyId { id: 99 }));
+    }
+
+    #[test]
+    fn migrate_rejects_downgrade() {
+        let mut deps = mock_dependencies();
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "9.9.9").unwrap();
+
+        let msg = MigrateMsg {
+            cw721_code_id: None,
+        };
+        let err = migrate(deps.as_mut(), mock_env(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::Std(_)));
+    }
+
+    #[test]
+    fn migrate_from_v0_1_requires_cw721_code() {
+        let mut deps = mock_dependencies();
+        set_contract_version(deps.as_mut().storage, CONTRACT_NAME, "0.1.0").unwrap();
+
+        let msg = MigrateMsg {
+            cw721_code_id: None,
+        };
+        let err = migrate(deps.as_mut(), mock_env(), msg).unwrap_err();
+        assert_eq!(err, ContractError::MissingCw721CodeId {});
+    }
+
+    #[test]
+    fn migrate_rejects_other_contract() {
+        let mut deps = mock_dependencies();
+        set_contract_version(deps.as_mut().storage, "crates.io:cw20-base", "0.1.0").unwrap();
+
+        let msg = MigrateMsg {
+            cw721_code_id: None,
+        };
+        let err = migrate(deps.as_mut(), mock_env(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::Std(_)));
+    }
+}
@@ -30,10 +30,20 @@ pub struct MsgInstantiateContract2 {
     pub fix_msg: bool,
 }
 
+#[derive(Clone, PartialEq, Message)]
+pub struct MsgInstantiateContract2Response {
+    /// Address is the bech32 address of the new contract instance.
+    #[prost(string, tag = "1")]
+    pub address: ::prost::alloc::string::String,
+    /// Data contains bytes to returned from the contract
+    #[prost(bytes = "vec", tag = "2")]
+    pub data: ::prost::alloc::vec::Vec<u8>,
+}
+
 #[derive(Clone, PartialEq, Message)]
 pub struct CosmosCoin {
     #[prost(string, tag = "1")]
     pub denom: ::prost::alloc::string::String,
     #[prost(string, tag = "2")]
     pub amount: ::prost::alloc::string::String,
-}
\ No newline at end of file
+}
@@ -1,16 +1,74 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Uint128};
+use cw_ownable::OwnershipError;
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, PartialEq)]
 pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error(transparent)]
+    Ownership(#[from] OwnershipError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
     #[error("Custom Error val: {val:?}")]
     CustomError { val: String },
+
+    #[error("Unknown reply id: {id}")]
+    UnknownReplyId { id: u64 },
+
+    #[error("Instantiated contract address not found in reply")]
+    NoInstantiatedAddress {},
+
+    #[error("Instantiated contract address mismatch: expected {expected}, got {actual}")]
+    InstantiatedAddressMismatch { expected: String, actual: String },
+
+    #[error("No funds sent")]
+    NoFunds {},
+
+    #[error("Only one denom can be sent")]
+    MultipleDenoms {},
+
+    #[error("Wrong denom: expected {expected}, got {got}")]
+    WrongDenom { expected: String, got: String },
+
+    #[error("Delegation amount {got} is below the minimum {minimum}")]
+    BelowMinimum { minimum: Uint128, got: Uint128 },
+
+    #[error("Invalid validator: {validator}")]
+    InvalidValidator { validator: String },
+
+    #[error("Validator {validator} is not in the allow list")]
+    ValidatorNotAllowed { validator: String },
+
+    #[error("Nothing to claim")]
+    NothingToClaim {},
+
+    #[error("No positions created before 0.2.0 are left to migrate")]
+    NothingToMigrate {},
+
+    #[error("Migrating from 0.1.x requires the code id of the record cw721")]
+    MissingCw721CodeId {},
+
+    #[error("Fee of {fee_bps} bps exceeds the maximum of {max_fee_bps} bps")]
+    FeeTooHigh { fee_bps: u16, max_fee_bps: u16 },
+
+    #[error("Redeeming {amount} LSCT yields no stake")]
+    NothingToUndelegate { amount: Uint128 },
+
+    #[error("Invalid position id: {id}")]
+    InvalidPositionId { id: String },
+
+    #[error("{address} is not a position token")]
+    UnknownPositionToken { address: String },
+
+    #[error("Closing requires the whole supply of {supply} LSCT, the sender holds {balance}")]
+    PositionNotFullyOwned { balance: Uint128, supply: Uint128 },
+
+    #[error("Position {id} is closed")]
+    PositionClosed { id: String },
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
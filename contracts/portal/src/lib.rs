@@ -1,8 +1,15 @@
 pub mod contract;
+pub mod cosmos_msg;
 mod error;
+pub mod metadata;
 pub mod msg;
-pub mod state;
-pub mod cosmos_msg;
+pub mod position_id;
 pub mod querier;
+pub mod state;
+
+#[cfg(test)]
+mod multi_tests;
+#[cfg(test)]
+pub mod test_app;
 
 pub use crate::error::ContractError;
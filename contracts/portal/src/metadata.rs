@@ -0,0 +1,229 @@
+use cosmwasm_std::{to_json_vec, Binary, StdResult};
+use cw20::Logo;
+use cw20_base::msg::InstantiateMarketingInfo;
+use serde::Serialize;
+
+use crate::position_id::PositionId;
+use crate::state::TokenMetadata;
+
+/// Used when a template renders to fewer bytes than cw20 accepts, or without a moniker
+pub const DEFAULT_NAME: &str = "Liquid Staking Contract Token";
+pub const DEFAULT_SYMBOL: &str = "LSCT";
+
+// bounds of cw20-base `InstantiateMsg::validate`
+const MIN_LEN: usize = 3;
+const MAX_NAME_LEN: usize = 50;
+const MAX_SYMBOL_LEN: usize = 12;
+
+impl Default for TokenMetadata {
+    fn default() -> Self {
+        TokenMetadata {
+            name_template: "{moniker} Liquid Staking #{seq}".to_string(),
+            symbol_template: "ls{moniker}".to_string(),
+            description_template: Some(
+                "Liquid staking position {id}, delegated to {moniker} ({validator})".to_string(),
+            ),
+            logo_url: None,
+        }
+    }
+}
+
+impl TokenMetadata {
+    /// Token name, cut to 50 bytes
+    pub fn name(&self, moniker: Option<&str>, id: &PositionId) -> String {
+        let moniker = match sanitize(moniker) {
+            Some(moniker) => moniker,
+            None => return DEFAULT_NAME.to_string(),
+        };
+        let mut name = render(&self.name_template, &moniker, id);
+        let mut end = name.len().min(MAX_NAME_LEN);
+        while !name.is_char_boundary(end) {
+            end -= 1;
+        }
+        name.truncate(end);
+        let name = name.trim().to_string();
+        if name.len() < MIN_LEN {
+            return DEFAULT_NAME.to_string();
+        }
+        name
+    }
+
+    /// Token symbol, keeping up to 12 of the letters and dashes cw20 accepts
+    pub fn symbol(&self, moniker: Option<&str>, id: &PositionId) -> String {
+        let moniker = match sanitize(moniker) {
+            Some(moniker) => moniker,
+            None => return DEFAULT_SYMBOL.to_string(),
+        };
+        let symbol: String = render(&self.symbol_template, &moniker, id)
+            .chars()
+            .filter(|c| c.is_ascii_alphabetic() || *c == '-')
+            .take(MAX_SYMBOL_LEN)
+            .collect();
+        if symbol.len() < MIN_LEN {
+            return DEFAULT_SYMBOL.to_string();
+        }
+        symbol
+    }
+
+    /// Marketing info of the token, unset without a description or a logo.
+    /// The validator address stands in for a missing moniker.
+    pub fn marketing(
+        &self,
+        moniker: Option<&str>,
+        id: &PositionId,
+    ) -> Option<InstantiateMarketingInfo> {
+        if self.description_template.is_none() && self.logo_url.is_none() {
+            return None;
+        }
+        let moniker = sanitize(moniker).unwrap_or_else(|| id.validator.clone());
+        Some(InstantiateMarketingInfo {
+            project: None,
+            description: self
+                .description_template
+                .as_ref()
+                .map(|template| render(template, &moniker, id)),
+            marketing: None,
+            logo: self.logo_url.clone().map(Logo::Url),
+        })
+    }
+}
+
+/// ERC721 metadata JSON of a record, rendered as a data uri so wallets need no gateway
+pub fn record_token_uri(record: &cw721_base::Metadata) -> StdResult<String> {
+    #[derive(Serialize)]
+    struct Attribute {
+        trait_type: &'static str,
+        value: String,
+    }
+
+    #[derive(Serialize)]
+    struct Json<'a> {
+        name: &'a str,
+        description: &'a Option<String>,
+        attributes: Vec<Attribute>,
+    }
+
+    let attribute = |trait_type: &'static str, value: String| Attribute { trait_type, value };
+    let json = to_json_vec(&Json {
+        name: &record.name,
+        description: &record.description,
+        attributes: vec![
+            attribute("validator", record.validator.clone()),
+            attribute("delegated", record.delegated.to_string()),
+            attribute("initial_amount", record.initial_amount.to_string()),
+            attribute("created_height", record.created_height.to_string()),
+        ],
+    })?;
+    Ok(format!(
+        "data:application/json;base64,{}",
+        Binary::from(json).to_base64()
+    ))
+}
+
+/// Moniker without control characters and surrounding spaces, `None` when nothing is left
+fn sanitize(moniker: Option<&str>) -> Option<String> {
+    let moniker: String = moniker?.chars().filter(|c| !c.is_control()).collect();
+    let moniker = moniker.trim();
+    if moniker.is_empty() {
+        return None;
+    }
+    Some(moniker.to_string())
+}
+
+/// Replaces `{moniker}`, `{validator}`, `{seq}` and `{id}` in `template`
+fn render(template: &str, moniker: &str, id: &PositionId) -> String {
+    template
+        .replace("{moniker}", moniker)
+        .replace("{validator}", &id.validator)
+        .replace("{seq}", &id.seq.to_string())
+        .replace("{id}", &id.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id() -> PositionId {
+        PositionId::new("linkvaloper1validator", 7)
+    }
+
+    #[test]
+    fn default_templates() {
+        let metadata = TokenMetadata::default();
+        assert_eq!(
+            metadata.name(Some("Cony"), &id()),
+            "Cony Liquid Staking #7".to_string()
+        );
+        assert_eq!(metadata.symbol(Some("Cony"), &id()), "lsCony".to_string());
+        assert_eq!(
+            metadata.marketing(Some("Cony"), &id()).unwrap().description,
+            Some("Liquid staking position linkvaloper1validator/7, delegated to Cony (linkvaloper1validator)".to_string())
+        );
+    }
+
+    #[test]
+    fn rendered_within_cw20_bounds() {
+        let metadata = TokenMetadata::default();
+        // the name is cut at the char boundary before 50 bytes
+        let moniker = format!("a{}", "é".repeat(30));
+        let name = metadata.name(Some(&moniker), &id());
+        assert_eq!(name, format!("a{}", "é".repeat(24)));
+        assert_eq!(metadata.symbol(Some(&moniker), &id()), "lsa".to_string());
+        let moniker = "Ünïcödé Validator 42";
+        assert_eq!(
+            metadata.symbol(Some(moniker), &id()),
+            "lsncdValidat".to_string()
+        );
+
+        // too little is left of the templates, fall back to the defaults
+        let metadata = TokenMetadata {
+            name_template: "{moniker}".to_string(),
+            symbol_template: "{seq}".to_string(),
+            description_template: None,
+            logo_url: None,
+        };
+        assert_eq!(metadata.name(Some("X"), &id()), DEFAULT_NAME.to_string());
+        assert_eq!(
+            metadata.symbol(Some("X"), &id()),
+            DEFAULT_SYMBOL.to_string()
+        );
+        assert_eq!(metadata.marketing(Some("X"), &id()), None);
+    }
+
+    #[test]
+    fn defaults_without_moniker() {
+        let metadata = TokenMetadata::default();
+        for moniker in [None, Some(""), Some(" \t\n"), Some("\u{7}")] {
+            assert_eq!(metadata.name(moniker, &id()), DEFAULT_NAME.to_string());
+            assert_eq!(metadata.symbol(moniker, &id()), DEFAULT_SYMBOL.to_string());
+            assert_eq!(
+                metadata.marketing(moniker, &id()).unwrap().description,
+                Some("Liquid staking position linkvaloper1validator/7, delegated to linkvaloper1validator (linkvaloper1validator)".to_string())
+            );
+        }
+
+        // control characters are dropped from the moniker
+        assert_eq!(
+            metadata.name(Some(" Co\nny "), &id()),
+            "Cony Liquid Staking #7".to_string()
+        );
+    }
+
+    #[test]
+    fn record_token_uri_embeds_json() {
+        let record = cw721_base::Metadata {
+            name: "Cony Liquid Staking #7".to_string(),
+            validator: "linkvaloper1validator".to_string(),
+            delegated: cosmwasm_std::coin(1_500, "cony"),
+            initial_amount: cosmwasm_std::coin(1_000, "cony"),
+            created_height: 12,
+            ..cw721_base::Metadata::default()
+        };
+        let uri = record_token_uri(&record).unwrap();
+        let json = uri.strip_prefix("data:application/json;base64,").unwrap();
+        assert_eq!(
+            String::from_utf8(Binary::from_base64(json).unwrap().to_vec()).unwrap(),
+            r#"{"name":"Cony Liquid Staking #7","description":null,"attributes":[{"trait_type":"validator","value":"linkvaloper1validator"},{"trait_type":"delegated","value":"1500cony"},{"trait_type":"initial_amount","value":"1000cony"},{"trait_type":"created_height","value":"12"}]}"#
+        );
+    }
+}
@@ -1,5 +1,10 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::Uint128;
+use cosmwasm_std::{Coin, Decimal, Timestamp, Uint128};
+use cw20::Cw20ReceiveMsg;
+use cw721::Cw721ReceiveMsg;
+use cw_ownable::{cw_ownable_execute, cw_ownable_query};
+
+use crate::position_id::PositionId;
 
 /// Message type for `instantiate` entry_point
 #[cw_serde]
@@ -7,21 +12,126 @@ pub struct InstantiateMsg {
     pub cw20_code_id: u64,
     pub cw721_code_id: u64,
     pub delegator_code_id: u64,
+    /// Smallest amount accepted by `DelegateAndTokenize`, defaults to 1
+    pub min_delegation: Option<Uint128>,
 }
 
 /// Message type for `execute` entry_point
+#[cw_ownable_execute]
 #[cw_serde]
 pub enum ExecuteMsg {
-    DelegateAndTokenize { validator: String },
-    WithdrawAllReward {},
-    Undelegate { id: String, amount: Uint128 },
+    DelegateAndTokenize {
+        validator: String,
+    },
+    /// Withdraw the rewards of every record the sender owns to the position tokens, up to
+    /// 100 per call. When more remain, the response carries a `next_start_after` attribute.
+    WithdrawAllReward {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Withdraw the rewards of a position whose record the sender owns to its token,
+    /// where holders claim their share
+    WithdrawReward {
+        id: PositionId,
+    },
+    /// Withdraw the rewards of several positions whose records the sender owns to their tokens
+    WithdrawRewards {
+        ids: Vec<PositionId>,
+    },
+    Undelegate {
+        id: PositionId,
+        amount: Uint128,
+    },
+    /// Claim every unbonding of the sender the chain has released, which happens at the end
+    /// of the block reaching its completion time. The others stay recorded
+    Claim {},
+    /// Add the attached coins to an existing position and mint LSCT to the sender
+    Delegate {
+        id: PositionId,
+    },
+    /// Move the stake of a position whose record the sender owns to another validator
+    Redelegate {
+        id: PositionId,
+        dst_validator: String,
+    },
+    /// Restake the rewards of a position whose record the sender owns.
+    /// Rewards in denoms other than the bonded one are sent to the sender
+    Compound {
+        id: PositionId,
+    },
+    /// Only callable by the owner
+    UpdateValidatorAllowList {
+        /// Restrict `DelegateAndTokenize` to the listed validators
+        enabled: bool,
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
+    /// Only callable by the owner. Takes `fee_bps` of every reward withdrawal,
+    /// up to 3000 (30%), and sends it to the treasury.
+    UpdateFeeConfig {
+        fee_bps: u16,
+        treasury: String,
+    },
+    /// Only callable by the owner. Fields left unset keep their current value,
+    /// new code ids only apply to positions created afterwards.
+    UpdateConfig {
+        cw20_code_id: Option<u64>,
+        delegator_code_id: Option<u64>,
+        min_delegation: Option<Uint128>,
+    },
+    /// Only callable by the owner. Templates of the token metadata of positions created
+    /// afterwards, where `{moniker}`, `{validator}`, `{seq}` and `{id}` are replaced.
+    /// Names are cut to 50 bytes and symbols keep up to 12 letters and dashes.
+    UpdateTokenMetadata {
+        name_template: String,
+        symbol_template: String,
+        description_template: Option<String>,
+        logo_url: Option<String>,
+    },
+    /// Hook of the position tokens, see `ReceiveMsg`
+    Receive(Cw20ReceiveMsg),
+    /// Hook of the records, see `NftReceiveMsg`
+    ReceiveNft(Cw721ReceiveMsg),
+    /// Only callable by the owner. Migrates the delegators of the positions to `new_code_id`.
+    MigrateDelegators {
+        ids: Vec<PositionId>,
+        new_code_id: u64,
+    },
+    /// Only callable by the owner. Moves up to `limit` positions created before 0.2.0, 30 by
+    /// default, to the current layout and migrates their tokens and delegators to the given
+    /// codes. Call again while the response carries `next_start_after`.
+    MigrateLegacyPositions {
+        cw20_code_id: u64,
+        delegator_code_id: u64,
+        limit: Option<u32>,
+    },
+}
+
+/// Messages embedded in a cw20 `Send` of position tokens to the portal
+#[cw_serde]
+pub enum ReceiveMsg {
+    /// Burn the sent tokens and undelegate their stake for the sender, in one step
+    Undelegate {},
+}
+
+/// Messages embedded in a cw721 `SendNft` of a record to the portal
+#[cw_serde]
+pub enum NftReceiveMsg {
+    /// Close the position: burn its whole LSCT supply, which the sender must hold and allow
+    /// the portal to burn, undelegate its whole stake for the sender and burn the record
+    Close {},
 }
 
 /// Message type for `migrate` entry_point
 #[cw_serde]
-pub enum MigrateMsg {}
+pub struct MigrateMsg {
+    /// Code the record cw721 migrates to, required when migrating from 0.1.x whose cw721
+    /// cannot hold the record metadata
+    pub cw721_code_id: Option<u64>,
+}
 
 /// Message type for `query` entry_point
+#[cw_ownable_query]
 #[cw_serde]
 #[derive(QueryResponses)]
 pub enum QueryMsg {
@@ -29,10 +139,48 @@ pub enum QueryMsg {
     Env {},
 
     #[returns(DataResponse)]
-    Data { id: String },
+    Data { id: PositionId },
 
+    /// Position ids, in key order
     #[returns(AllResponse)]
-    All {},
+    All {
+        start_after: Option<PositionId>,
+        limit: Option<u32>,
+    },
+
+    /// Ids of the positions currently delegated to `validator`
+    #[returns(AllResponse)]
+    ByValidator {
+        validator: String,
+        start_after: Option<PositionId>,
+        limit: Option<u32>,
+    },
+
+    #[returns(PositionResponse)]
+    Position { id: PositionId },
+
+    /// Position of an LSCT contract
+    #[returns(PositionResponse)]
+    PositionByToken { address: String },
+
+    /// Position of a delegator contract
+    #[returns(PositionResponse)]
+    PositionByDelegator { address: String },
+
+    #[returns(ValidatorAllowListResponse)]
+    ValidatorAllowList {},
+
+    #[returns(ClaimsResponse)]
+    Claims { address: String },
+
+    #[returns(ExchangeRateResponse)]
+    ExchangeRate { id: PositionId },
+
+    #[returns(FeeConfigResponse)]
+    FeeConfig {},
+
+    #[returns(TokenMetadataResponse)]
+    TokenMetadata {},
 }
 
 // We define a custom struct for each query response
@@ -41,15 +189,84 @@ pub struct EnvResponse {
     pub cw20_code_id: u64,
     pub cw721_address: String,
     pub delegator_code_id: u64,
+    pub min_delegation: Uint128,
 }
 
 #[cw_serde]
 pub struct DataResponse {
     pub token_address: String,
     pub delegator_address: String,
+    pub validator: String,
 }
 
 #[cw_serde]
 pub struct AllResponse {
-    pub data: Vec<String>,
+    pub data: Vec<PositionId>,
+}
+
+#[cw_serde]
+pub struct PositionResponse {
+    pub id: PositionId,
+    /// Current validator
+    pub validator: String,
+    /// Owner of the record, unset once the position is closed
+    pub owner: Option<String>,
+    pub token_address: String,
+    pub delegator_address: String,
+    /// Native tokens actually delegated by the position
+    pub delegated: Uint128,
+    /// Rewards accumulated by the delegation and not withdrawn yet
+    pub pending_rewards: Vec<Coin>,
+    /// LSCT supply of the position
+    pub supply: Uint128,
+    /// Block height the position was created at, 0 for positions created before 0.2.0
+    pub created_at: u64,
+    /// Block height the position was closed at
+    pub closed_at: Option<u64>,
+}
+
+#[cw_serde]
+pub struct FeeConfigResponse {
+    pub fee_bps: u16,
+    /// Unset while no fee was configured
+    pub treasury: Option<String>,
+    pub max_fee_bps: u16,
+}
+
+#[cw_serde]
+pub struct TokenMetadataResponse {
+    pub name_template: String,
+    pub symbol_template: String,
+    pub description_template: Option<String>,
+    pub logo_url: Option<String>,
+}
+
+#[cw_serde]
+pub struct ValidatorAllowListResponse {
+    pub enabled: bool,
+    pub validators: Vec<String>,
+}
+
+#[cw_serde]
+pub struct ClaimsResponse {
+    pub claims: Vec<ClaimInfo>,
+}
+
+#[cw_serde]
+pub struct ClaimInfo {
+    pub id: PositionId,
+    pub delegator_address: String,
+    /// Undelegated stake, a claim pays less when a slash hit the unbonding
+    pub amount: Uint128,
+    pub completion_time: Timestamp,
+}
+
+#[cw_serde]
+pub struct ExchangeRateResponse {
+    /// Native tokens actually delegated by the position
+    pub delegated: Uint128,
+    /// LSCT supply of the position
+    pub supply: Uint128,
+    /// Native tokens redeemed per LSCT
+    pub rate: Decimal,
 }
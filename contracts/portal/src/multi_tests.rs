@@ -0,0 +1,2035 @@
+use cosmwasm_std::{
+    coin, coins, to_json_binary, Addr, Binary, Decimal, Empty, StdResult, Uint128, WasmMsg,
+};
+use cw20::Cw20ReceiveMsg;
+use cw_multi_test::{AppResponse, Executor};
+use cw_ownable::{Ownership, OwnershipError};
+
+use crate::metadata::{record_token_uri, DEFAULT_NAME, DEFAULT_SYMBOL};
+use crate::msg::{
+    AllResponse, ClaimInfo, ClaimsResponse, EnvResponse, ExecuteMsg, FeeConfigResponse, MigrateMsg,
+    NftReceiveMsg, PositionResponse, QueryMsg, ReceiveMsg, TokenMetadataResponse,
+    ValidatorAllowListResponse,
+};
+use crate::position_id::PositionId;
+use crate::state::{positions, v0_1, POSITION_SEQ};
+use crate::test_app::{
+    cw20_contract, delegator_contract, legacy_cw721_contract, Suite, ANONYMOUS_VALIDATOR, DENOM,
+    INITIAL_BALANCE, JAILED_VALIDATOR, MONIKER, OTHER_DENOM, OTHER_MONIKER, OTHER_VALIDATOR,
+    UNBONDING_TIME, VALIDATOR,
+};
+use crate::ContractError;
+
+const YEAR: u64 = 60 * 60 * 24 * 365;
+
+fn attribute(res: &AppResponse, key: &str) -> Option<String> {
+    res.events
+        .iter()
+        .flat_map(|event| event.attributes.iter())
+        .find(|attr| attr.key == key)
+        .map(|attr| attr.value.clone())
+}
+
+fn ls_id(n: u32) -> PositionId {
+    PositionId::new(VALIDATOR, n)
+}
+
+fn positions_by_validator(suite: &Suite, validator: &str) -> Vec<PositionId> {
+    let res: AllResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.portal,
+            &QueryMsg::ByValidator {
+                validator: validator.to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    res.data
+}
+
+/// Rewards withdrawn to a position token and not claimed yet.
+fn token_rewards(suite: &Suite, id: &PositionId) -> Uint128 {
+    suite.balance(&Addr::unchecked(suite.data(id).token_address))
+}
+
+#[test]
+fn instantiate_creates_record_collection() {
+    let suite = Suite::new();
+
+    let info: cw721::ContractInfoResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.cw721,
+            &cw721_base::QueryMsg::<Empty>::ContractInfo {},
+        )
+        .unwrap();
+    assert_eq!(info.name, "Liquid Staking Contract Record");
+    assert_eq!(info.symbol, "SLCR");
+}
+
+#[test]
+fn delegate_withdraw_and_undelegate() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+
+    // delegate and tokenize
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    let data = suite.data(&ls_id(0));
+
+    assert_eq!(
+        suite.balance(&user),
+        Uint128::new(INITIAL_BALANCE - 1_000_000)
+    );
+    assert_eq!(
+        suite.cw20_balance(&data.token_address, &user),
+        Uint128::new(1_000_000)
+    );
+    assert_eq!(suite.nft_owner(&ls_id(0)), user.to_string());
+    let delegation = suite.delegation(&data.delegator_address).unwrap();
+    assert_eq!(delegation.amount.amount, Uint128::new(1_000_000));
+
+    // withdraw a year of rewards to the token, the only holder claims them all
+    suite.advance_time(YEAR);
+    suite.withdraw_all_reward(&user).unwrap();
+    assert_eq!(token_rewards(&suite, &ls_id(0)), Uint128::new(100_000));
+    let before = suite.balance(&user);
+    suite.claim_rewards(&user, &ls_id(0)).unwrap();
+    assert_eq!(suite.balance(&user) - before, Uint128::new(100_000));
+
+    // undelegate part of the position
+    suite.undelegate(&user, &ls_id(0), 400_000).unwrap();
+    assert_eq!(
+        suite.cw20_balance(&data.token_address, &user),
+        Uint128::new(600_000)
+    );
+    let delegation = suite.delegation(&data.delegator_address).unwrap();
+    assert_eq!(delegation.amount.amount, Uint128::new(600_000));
+}
+
+#[test]
+fn positions_are_numbered_per_validator() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000, DENOM))
+        .unwrap();
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(2_000, DENOM))
+        .unwrap();
+
+    let first = suite.data(&ls_id(0));
+    let second = suite.data(&ls_id(1));
+    assert_ne!(first.delegator_address, second.delegator_address);
+    assert_eq!(
+        suite.cw20_balance(&second.token_address, &user),
+        Uint128::new(2_000)
+    );
+    assert_eq!(suite.nft_owner(&ls_id(1)), user.to_string());
+}
+
+#[test]
+fn delegate_requires_funds() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+
+    let err = suite
+        .delegate_and_tokenize(&user, VALIDATOR, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::NoFunds {}
+    );
+}
+
+#[test]
+fn delegate_rejects_multiple_denoms() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+
+    let err = suite
+        .delegate_and_tokenize(
+            &user,
+            VALIDATOR,
+            &[coin(1_000, DENOM), coin(1_000, OTHER_DENOM)],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::MultipleDenoms {}
+    );
+}
+
+#[test]
+fn delegate_rejects_wrong_denom() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+
+    let err = suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000, OTHER_DENOM))
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::WrongDenom {
+            expected: DENOM.to_string(),
+            got: OTHER_DENOM.to_string(),
+        }
+    );
+}
+
+#[test]
+fn delegate_rejects_amount_below_minimum() {
+    let mut suite = Suite::init(Some(Uint128::new(1_000)));
+    let user = suite.user.clone();
+
+    let err = suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(999, DENOM))
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::BelowMinimum {
+            minimum: Uint128::new(1_000),
+            got: Uint128::new(999),
+        }
+    );
+
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000, DENOM))
+        .unwrap();
+}
+
+#[test]
+fn delegate_rejects_unknown_validator() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+
+    let err = suite
+        .delegate_and_tokenize(&user, "linkvaloper1typo", &coins(1_000, DENOM))
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidValidator {
+            validator: "linkvaloper1typo".to_string(),
+        }
+    );
+}
+
+#[test]
+fn delegate_rejects_jailed_validator() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+
+    let err = suite
+        .delegate_and_tokenize(&user, JAILED_VALIDATOR, &coins(1_000, DENOM))
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidValidator {
+            validator: JAILED_VALIDATOR.to_string(),
+        }
+    );
+}
+
+#[test]
+fn validator_allow_list() {
+    let mut suite = Suite::new();
+    let owner = suite.owner.clone();
+    let user = suite.user.clone();
+
+    // only the owner manages the allow list
+    let err = suite
+        .update_validator_allow_list(&user, true, &[OTHER_VALIDATOR], &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Ownership(OwnershipError::NotOwner)
+    );
+
+    // only validators known to the staking module can be listed
+    let err = suite
+        .update_validator_allow_list(&owner, true, &[OTHER_VALIDATOR, "linkvaloper1typo"], &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::InvalidValidator {
+            validator: "linkvaloper1typo".to_string(),
+        }
+    );
+
+    suite
+        .update_validator_allow_list(&owner, true, &[OTHER_VALIDATOR], &[])
+        .unwrap();
+    let res: ValidatorAllowListResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.portal, &QueryMsg::ValidatorAllowList {})
+        .unwrap();
+    assert_eq!(
+        res,
+        ValidatorAllowListResponse {
+            enabled: true,
+            validators: vec![OTHER_VALIDATOR.to_string()],
+        }
+    );
+
+    let err = suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000, DENOM))
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::ValidatorNotAllowed {
+            validator: VALIDATOR.to_string(),
+        }
+    );
+    suite
+        .delegate_and_tokenize(&user, OTHER_VALIDATOR, &coins(1_000, DENOM))
+        .unwrap();
+
+    // disabling the list lets any bonded validator through again
+    suite
+        .update_validator_allow_list(&owner, false, &[], &[])
+        .unwrap();
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000, DENOM))
+        .unwrap();
+}
+
+#[test]
+fn claim_unbonded_tokens() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    let data = suite.data(&ls_id(0));
+    suite.undelegate(&user, &ls_id(0), 400_000).unwrap();
+
+    let claims: ClaimsResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.portal,
+            &QueryMsg::Claims {
+                address: user.to_string(),
+            },
+        )
+        .unwrap();
+    let completion_time = suite.app.block_info().time.plus_seconds(UNBONDING_TIME);
+    assert_eq!(
+        claims.claims,
+        vec![ClaimInfo {
+            id: ls_id(0),
+            delegator_address: data.delegator_address.clone(),
+            amount: Uint128::new(400_000),
+            completion_time,
+        }]
+    );
+
+    // nothing is claimable while unbonding
+    let err = suite.claim(&user).unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::NothingToClaim {}
+    );
+
+    suite.advance_time(UNBONDING_TIME);
+    suite.process_unbondings();
+    let before = suite.balance(&user);
+    suite.claim(&user).unwrap();
+    assert_eq!(suite.balance(&user) - before, Uint128::new(400_000));
+
+    let claims: ClaimsResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.portal,
+            &QueryMsg::Claims {
+                address: user.to_string(),
+            },
+        )
+        .unwrap();
+    assert!(claims.claims.is_empty());
+}
+
+#[test]
+fn withdraw_reward_per_position() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+    let buyer = suite.app.api().addr_make("buyer");
+
+    for _ in 0..3 {
+        suite
+            .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+            .unwrap();
+    }
+    suite.advance_time(YEAR);
+
+    // harvest a single position
+    suite
+        .app
+        .execute_contract(
+            user.clone(),
+            suite.portal.clone(),
+            &ExecuteMsg::WithdrawReward { id: ls_id(0) },
+            &[],
+        )
+        .unwrap();
+    assert_eq!(token_rewards(&suite, &ls_id(0)), Uint128::new(100_000));
+    assert_eq!(token_rewards(&suite, &ls_id(1)), Uint128::zero());
+
+    // harvest the rest in one go, duplicates are ignored
+    suite
+        .withdraw_rewards(&user, &[ls_id(1), ls_id(2), ls_id(2)])
+        .unwrap();
+    assert_eq!(token_rewards(&suite, &ls_id(1)), Uint128::new(100_000));
+    assert_eq!(token_rewards(&suite, &ls_id(2)), Uint128::new(100_000));
+
+    // the record owner harvests, yet gets nothing once the tokens changed hands
+    suite.transfer_nft(&user, &buyer, &ls_id(1)).unwrap();
+    suite.advance_time(YEAR);
+    let err = suite.withdraw_rewards(&user, &[ls_id(1)]).unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+    suite.withdraw_rewards(&buyer, &[ls_id(1)]).unwrap();
+    assert_eq!(suite.balance(&buyer), Uint128::zero());
+    let before = suite.balance(&user);
+    suite.claim_rewards(&user, &ls_id(1)).unwrap();
+    assert_eq!(suite.balance(&user) - before, Uint128::new(200_000));
+    let err = suite.claim_rewards(&buyer, &ls_id(1)).unwrap_err();
+    assert_eq!(
+        err.downcast::<cw20_base::ContractError>().unwrap(),
+        cw20_base::ContractError::NoRewards {}
+    );
+}
+
+#[test]
+fn bought_tokens_cannot_harvest() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+    let buyer = suite.app.api().addr_make("buyer");
+
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    suite.advance_time(YEAR);
+
+    // buying tokens after a year of rewards does not let the buyer harvest them at once
+    suite
+        .transfer_tokens(&user, &ls_id(0), &buyer, 500_000)
+        .unwrap();
+    let err = suite.withdraw_rewards(&buyer, &[ls_id(0)]).unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+    let err = suite
+        .app
+        .execute_contract(
+            buyer.clone(),
+            suite.portal.clone(),
+            &ExecuteMsg::WithdrawReward { id: ls_id(0) },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+    assert_eq!(token_rewards(&suite, &ls_id(0)), Uint128::zero());
+    let err = suite.claim_rewards(&buyer, &ls_id(0)).unwrap_err();
+    assert_eq!(
+        err.downcast::<cw20_base::ContractError>().unwrap(),
+        cw20_base::ContractError::NoRewards {}
+    );
+}
+
+#[test]
+fn withdraw_rewards_skips_drained_positions() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+
+    for _ in 0..2 {
+        suite
+            .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+            .unwrap();
+    }
+    suite.undelegate(&user, &ls_id(0), 1_000_000).unwrap();
+    suite.advance_time(YEAR);
+
+    suite
+        .withdraw_rewards(&user, &[ls_id(0), ls_id(1)])
+        .unwrap();
+    assert_eq!(token_rewards(&suite, &ls_id(0)), Uint128::zero());
+    assert_eq!(token_rewards(&suite, &ls_id(1)), Uint128::new(100_000));
+    suite
+        .app
+        .execute_contract(
+            user.clone(),
+            suite.portal.clone(),
+            &ExecuteMsg::WithdrawReward { id: ls_id(0) },
+            &[],
+        )
+        .unwrap();
+}
+
+#[test]
+fn withdraw_all_reward_covers_every_record() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+
+    // more than the cw721 default page of ten
+    for _ in 0..12 {
+        suite
+            .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+            .unwrap();
+    }
+    suite.advance_time(YEAR);
+
+    let res = suite.withdraw_all_reward(&user).unwrap();
+    for n in 0..12 {
+        assert_eq!(token_rewards(&suite, &ls_id(n)), Uint128::new(100_000));
+    }
+    assert_eq!(attribute(&res, "records"), Some("12".to_string()));
+    assert_eq!(attribute(&res, "next_start_after"), None);
+}
+
+#[test]
+fn withdraw_all_reward_skips_drained_positions() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+
+    for _ in 0..3 {
+        suite
+            .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+            .unwrap();
+    }
+    // fully undelegated, but the record is still owned
+    suite.undelegate(&user, &ls_id(1), 1_000_000).unwrap();
+    suite.advance_time(YEAR);
+
+    let res = suite.withdraw_all_reward(&user).unwrap();
+    assert_eq!(attribute(&res, "records"), Some("3".to_string()));
+    assert_eq!(token_rewards(&suite, &ls_id(0)), Uint128::new(100_000));
+    assert_eq!(token_rewards(&suite, &ls_id(1)), Uint128::zero());
+    assert_eq!(token_rewards(&suite, &ls_id(2)), Uint128::new(100_000));
+}
+
+#[test]
+fn withdraw_all_reward_paginates() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+
+    for _ in 0..7 {
+        suite
+            .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+            .unwrap();
+    }
+    suite.advance_time(YEAR);
+
+    let withdraw_page = |suite: &mut Suite, start_after: Option<String>| {
+        suite
+            .app
+            .execute_contract(
+                user.clone(),
+                suite.portal.clone(),
+                &ExecuteMsg::WithdrawAllReward {
+                    start_after,
+                    limit: Some(5),
+                },
+                &[],
+            )
+            .unwrap()
+    };
+
+    let harvested = |suite: &Suite| {
+        (0..7)
+            .filter(|n| !token_rewards(suite, &ls_id(*n)).is_zero())
+            .count()
+    };
+
+    let res = withdraw_page(&mut suite, None);
+    assert_eq!(harvested(&suite), 5);
+    let next = attribute(&res, "next_start_after");
+    assert!(next.is_some());
+
+    let res = withdraw_page(&mut suite, next);
+    assert_eq!(harvested(&suite), 7);
+    assert_eq!(attribute(&res, "records"), Some("2".to_string()));
+    assert_eq!(attribute(&res, "next_start_after"), None);
+}
+
+#[test]
+fn compound_restakes_rewards() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    let data = suite.data(&ls_id(0));
+    suite.advance_time(YEAR);
+
+    let before = suite.balance(&user);
+    suite
+        .app
+        .execute_contract(
+            user.clone(),
+            suite.portal.clone(),
+            &ExecuteMsg::Compound { id: ls_id(0) },
+            &[],
+        )
+        .unwrap();
+
+    // rewards went back into the stake, not to the owner, and no LSCT was minted
+    assert_eq!(suite.balance(&user), before);
+    let delegation = suite.delegation(&data.delegator_address).unwrap();
+    assert_eq!(delegation.amount.amount, Uint128::new(1_100_000));
+    let token_info: cw20::TokenInfoResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&data.token_address, &cw20_base::msg::QueryMsg::TokenInfo {})
+        .unwrap();
+    assert_eq!(token_info.total_supply, Uint128::new(1_000_000));
+
+    // only the record owner may compound
+    let stranger = suite.app.api().addr_make("stranger");
+    let err = suite
+        .app
+        .execute_contract(
+            stranger,
+            suite.portal.clone(),
+            &ExecuteMsg::Compound { id: ls_id(0) },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+}
+
+#[test]
+fn top_up_existing_position() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+    let other = suite.app.api().addr_make("other");
+
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    let data = suite.data(&ls_id(0));
+
+    suite
+        .app
+        .execute_contract(
+            user.clone(),
+            suite.portal.clone(),
+            &ExecuteMsg::Delegate { id: ls_id(0) },
+            &coins(500_000, DENOM),
+        )
+        .unwrap();
+
+    // the same delegator and token grow, no new position is created
+    assert_eq!(
+        suite.cw20_balance(&data.token_address, &user),
+        Uint128::new(1_500_000)
+    );
+    let delegation = suite.delegation(&data.delegator_address).unwrap();
+    assert_eq!(delegation.amount.amount, Uint128::new(1_500_000));
+    let all: AllResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.portal,
+            &QueryMsg::All {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(all.data, vec![ls_id(0)]);
+
+    // funds are validated like for new positions
+    let err = suite
+        .app
+        .execute_contract(
+            other,
+            suite.portal.clone(),
+            &ExecuteMsg::Delegate { id: ls_id(0) },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::NoFunds {}
+    );
+}
+
+#[test]
+fn redelegate_position() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    let before = suite.data(&ls_id(0));
+
+    let redelegate = |suite: &mut Suite, sender: &cosmwasm_std::Addr| {
+        suite.app.execute_contract(
+            sender.clone(),
+            suite.portal.clone(),
+            &ExecuteMsg::Redelegate {
+                id: ls_id(0),
+                dst_validator: OTHER_VALIDATOR.to_string(),
+            },
+            &[],
+        )
+    };
+
+    // only the record owner may move the stake
+    let stranger = suite.app.api().addr_make("stranger");
+    let err = redelegate(&mut suite, &stranger).unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Unauthorized {}
+    );
+
+    redelegate(&mut suite, &user).unwrap();
+
+    // same id, token and delegator, new validator
+    let after = suite.data(&ls_id(0));
+    assert_eq!(after.validator, OTHER_VALIDATOR);
+    assert_eq!(after.token_address, before.token_address);
+    assert_eq!(after.delegator_address, before.delegator_address);
+    assert_eq!(suite.delegation(&after.delegator_address), None);
+    let delegation = suite
+        .app
+        .wrap()
+        .query_delegation(&after.delegator_address, OTHER_VALIDATOR)
+        .unwrap()
+        .unwrap();
+    assert_eq!(delegation.amount.amount, Uint128::new(1_000_000));
+    assert_eq!(
+        suite.cw20_balance(&after.token_address, &user),
+        Uint128::new(1_000_000)
+    );
+    assert_eq!(suite.nft_owner(&ls_id(0)), user.to_string());
+
+    // the validator index follows the stake, new ids keep counting
+    assert!(positions_by_validator(&suite, VALIDATOR).is_empty());
+    assert_eq!(
+        positions_by_validator(&suite, OTHER_VALIDATOR),
+        vec![ls_id(0)]
+    );
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    assert_eq!(positions_by_validator(&suite, VALIDATOR), vec![ls_id(1)]);
+}
+
+#[test]
+fn rewards_are_shared_by_token_holders() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+    let holder = suite.app.api().addr_make("holder");
+
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    suite
+        .transfer_tokens(&user, &ls_id(0), &holder, 250_000)
+        .unwrap();
+
+    // a year of rewards is split pro rata
+    suite.advance_time(YEAR);
+    suite.withdraw_rewards(&user, &[ls_id(0)]).unwrap();
+    let before = suite.balance(&user);
+    suite.claim_rewards(&user, &ls_id(0)).unwrap();
+    assert_eq!(suite.balance(&user) - before, Uint128::new(75_000));
+    suite.claim_rewards(&holder, &ls_id(0)).unwrap();
+    assert_eq!(suite.balance(&holder), Uint128::new(25_000));
+
+    // rewards pending at a top-up are harvested before the new tokens are minted
+    suite.advance_time(YEAR);
+    suite
+        .app
+        .execute_contract(
+            user.clone(),
+            suite.portal.clone(),
+            &ExecuteMsg::Delegate { id: ls_id(0) },
+            &coins(1_000_000, DENOM),
+        )
+        .unwrap();
+    suite.claim_rewards(&holder, &ls_id(0)).unwrap();
+    assert_eq!(suite.balance(&holder), Uint128::new(50_000));
+}
+
+#[test]
+fn exchange_rate_follows_the_stake() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    let data = suite.data(&ls_id(0));
+    let rate = suite.exchange_rate(&ls_id(0));
+    assert_eq!(rate.rate, Decimal::one());
+
+    // compounding grows the stake behind each LSCT
+    suite.advance_time(YEAR);
+    suite
+        .app
+        .execute_contract(
+            user.clone(),
+            suite.portal.clone(),
+            &ExecuteMsg::Compound { id: ls_id(0) },
+            &[],
+        )
+        .unwrap();
+    let rate = suite.exchange_rate(&ls_id(0));
+    assert_eq!(rate.delegated, Uint128::new(1_100_000));
+    assert_eq!(rate.supply, Uint128::new(1_000_000));
+    assert_eq!(rate.rate, Decimal::percent(110));
+
+    // a top-up mints at the current rate
+    suite
+        .app
+        .execute_contract(
+            user.clone(),
+            suite.portal.clone(),
+            &ExecuteMsg::Delegate { id: ls_id(0) },
+            &coins(1_100_000, DENOM),
+        )
+        .unwrap();
+    assert_eq!(
+        suite.cw20_balance(&data.token_address, &user),
+        Uint128::new(2_000_000)
+    );
+
+    // and LSCT redeem at it
+    suite.undelegate(&user, &ls_id(0), 100_000).unwrap();
+    let delegation = suite.delegation(&data.delegator_address).unwrap();
+    assert_eq!(delegation.amount.amount, Uint128::new(2_090_000));
+    let claims: ClaimsResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.portal,
+            &QueryMsg::Claims {
+                address: user.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(claims.claims[0].amount, Uint128::new(110_000));
+}
+
+#[test]
+fn undelegate_after_slash() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    let data = suite.data(&ls_id(0));
+
+    suite.slash(VALIDATOR, 10);
+    let rate = suite.exchange_rate(&ls_id(0));
+    assert_eq!(rate.delegated, Uint128::new(900_000));
+    assert_eq!(rate.rate, Decimal::percent(90));
+
+    // the whole supply redeems exactly what is left, nobody is left short
+    let res = suite.undelegate(&user, &ls_id(0), 1_000_000).unwrap();
+    assert_eq!(attribute(&res, "stake"), Some("900000".to_string()));
+    let delegated = suite
+        .delegation(&data.delegator_address)
+        .map(|delegation| delegation.amount.amount)
+        .unwrap_or_default();
+    assert_eq!(delegated, Uint128::zero());
+    assert_eq!(
+        suite.cw20_balance(&data.token_address, &user),
+        Uint128::zero()
+    );
+}
+
+#[test]
+fn claim_after_slash_during_unbonding() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    suite.undelegate(&user, &ls_id(0), 400_000).unwrap();
+
+    // the chain releases 10% less than the portal recorded
+    suite.slash(VALIDATOR, 10);
+    suite.advance_time(UNBONDING_TIME);
+    suite.process_unbondings();
+
+    let balance = suite.balance(&user);
+    suite.claim(&user).unwrap();
+    assert_eq!(suite.balance(&user) - balance, Uint128::new(360_000));
+    let claims: ClaimsResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.portal,
+            &QueryMsg::Claims {
+                address: user.to_string(),
+            },
+        )
+        .unwrap();
+    assert!(claims.claims.is_empty());
+}
+
+#[test]
+fn claim_in_completion_block() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    let data = suite.data(&ls_id(0));
+    suite.undelegate(&user, &ls_id(0), 400_000).unwrap();
+
+    // the completion time is reached, but the chain only pays out at the end of the block
+    suite.advance_time(UNBONDING_TIME);
+    let err = suite.claim(&user).unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::NothingToClaim {}
+    );
+    let err = suite
+        .app
+        .execute_contract(
+            suite.portal.clone(),
+            Addr::unchecked(&data.delegator_address),
+            &delegator::msg::ExecuteMsg::Claim {
+                recipient: user.to_string(),
+                ids: vec![0],
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<delegator::ContractError>().unwrap(),
+        delegator::ContractError::UnbondingNotReleased { id: 0 }
+    );
+    let claims: ClaimsResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.portal,
+            &QueryMsg::Claims {
+                address: user.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(claims.claims.len(), 1);
+
+    suite.process_unbondings();
+    let balance = suite.balance(&user);
+    suite.claim(&user).unwrap();
+    assert_eq!(suite.balance(&user) - balance, Uint128::new(400_000));
+}
+
+#[test]
+fn slash_only_hits_earlier_unbondings() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+    let holder = suite.app.api().addr_make("holder");
+
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    suite
+        .transfer_tokens(&user, &ls_id(0), &holder, 300_000)
+        .unwrap();
+    suite.undelegate(&user, &ls_id(0), 400_000).unwrap();
+
+    // the slash hits the unbonding of the user and the stake, not the later unbonding
+    suite.slash(VALIDATOR, 10);
+    suite.advance_time(60);
+    suite.undelegate(&holder, &ls_id(0), 300_000).unwrap();
+    suite.advance_time(UNBONDING_TIME);
+    suite.process_unbondings();
+
+    // the user cannot be paid with the coins of the holder
+    let balance = suite.balance(&user);
+    suite.claim(&user).unwrap();
+    assert_eq!(suite.balance(&user) - balance, Uint128::new(360_000));
+    let balance = suite.balance(&holder);
+    suite.claim(&holder).unwrap();
+    assert_eq!(suite.balance(&holder) - balance, Uint128::new(270_000));
+}
+
+#[test]
+fn owner_updates_config() {
+    let mut suite = Suite::new();
+    let owner = suite.owner.clone();
+    let user = suite.user.clone();
+
+    let ownership: Ownership<Addr> = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.portal, &QueryMsg::Ownership {})
+        .unwrap();
+    assert_eq!(ownership.owner, Some(owner.clone()));
+
+    // roll out a new token code for the positions created from now on
+    let cw20_code_id = suite.app.store_code(cw20_contract());
+    let update = ExecuteMsg::UpdateConfig {
+        cw20_code_id: Some(cw20_code_id),
+        delegator_code_id: None,
+        min_delegation: Some(Uint128::new(1_000)),
+    };
+    let err = suite
+        .app
+        .execute_contract(user.clone(), suite.portal.clone(), &update, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Ownership(OwnershipError::NotOwner)
+    );
+    suite
+        .app
+        .execute_contract(owner, suite.portal.clone(), &update, &[])
+        .unwrap();
+
+    let env: EnvResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.portal, &QueryMsg::Env {})
+        .unwrap();
+    assert_eq!(env.cw20_code_id, cw20_code_id);
+    assert_eq!(env.delegator_code_id, suite.code_ids.delegator);
+    assert_eq!(env.min_delegation, Uint128::new(1_000));
+
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    let data = suite.data(&ls_id(0));
+    let token = suite
+        .app
+        .wrap()
+        .query_wasm_contract_info(&data.token_address)
+        .unwrap();
+    assert_eq!(token.code_id, cw20_code_id);
+}
+
+#[test]
+fn protocol_fee_on_rewards() {
+    let mut suite = Suite::new();
+    let owner = suite.owner.clone();
+    let user = suite.user.clone();
+    let treasury = suite.app.api().addr_make("treasury");
+
+    let update = |fee_bps| ExecuteMsg::UpdateFeeConfig {
+        fee_bps,
+        treasury: treasury.to_string(),
+    };
+
+    // the fee is capped and only the owner sets it
+    let err = suite
+        .app
+        .execute_contract(owner.clone(), suite.portal.clone(), &update(3_001), &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::FeeTooHigh {
+            fee_bps: 3_001,
+            max_fee_bps: 3_000
+        }
+    );
+    let err = suite
+        .app
+        .execute_contract(user.clone(), suite.portal.clone(), &update(1_000), &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Ownership(OwnershipError::NotOwner)
+    );
+    suite
+        .app
+        .execute_contract(owner, suite.portal.clone(), &update(1_000), &[])
+        .unwrap();
+    let config: FeeConfigResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.portal, &QueryMsg::FeeConfig {})
+        .unwrap();
+    assert_eq!(
+        config,
+        FeeConfigResponse {
+            fee_bps: 1_000,
+            treasury: Some(treasury.to_string()),
+            max_fee_bps: 3_000,
+        }
+    );
+
+    for _ in 0..2 {
+        suite
+            .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+            .unwrap();
+    }
+    suite.advance_time(YEAR);
+
+    // a tenth of every harvest goes to the treasury
+    let res = suite.withdraw_rewards(&user, &[ls_id(0)]).unwrap();
+    assert_eq!(token_rewards(&suite, &ls_id(0)), Uint128::new(90_000));
+    assert_eq!(suite.balance(&treasury), Uint128::new(10_000));
+    assert_eq!(
+        attribute(&res, "fee_amount"),
+        Some(format!("10000{}", DENOM))
+    );
+
+    // compounded rewards as well
+    let res = suite
+        .app
+        .execute_contract(
+            user.clone(),
+            suite.portal.clone(),
+            &ExecuteMsg::Compound { id: ls_id(1) },
+            &[],
+        )
+        .unwrap();
+    let data = suite.data(&ls_id(1));
+    let delegation = suite.delegation(&data.delegator_address).unwrap();
+    assert_eq!(delegation.amount.amount, Uint128::new(1_090_000));
+    assert_eq!(suite.balance(&treasury), Uint128::new(20_000));
+    assert_eq!(
+        attribute(&res, "fee_amount"),
+        Some(format!("10000{}", DENOM))
+    );
+}
+
+#[test]
+fn owner_migrates_delegators() {
+    let mut suite = Suite::new();
+    let owner = suite.owner.clone();
+    let user = suite.user.clone();
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+
+    let delegator_code_id = suite.app.store_code(delegator_contract());
+    let migrate = ExecuteMsg::MigrateDelegators {
+        ids: vec![ls_id(1), ls_id(0), ls_id(1)],
+        new_code_id: delegator_code_id,
+    };
+    let err = suite
+        .app
+        .execute_contract(user.clone(), suite.portal.clone(), &migrate, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Ownership(OwnershipError::NotOwner)
+    );
+
+    let res = suite
+        .app
+        .execute_contract(owner, suite.portal.clone(), &migrate, &[])
+        .unwrap();
+    assert_eq!(
+        attribute(&res, "new_code_id"),
+        Some(delegator_code_id.to_string())
+    );
+    for id in [ls_id(0), ls_id(1)] {
+        let data = suite.data(&id);
+        let delegator = suite
+            .app
+            .wrap()
+            .query_wasm_contract_info(&data.delegator_address)
+            .unwrap();
+        assert_eq!(delegator.code_id, delegator_code_id);
+    }
+
+    // the migrated delegators keep working
+    suite.advance_time(YEAR);
+    suite.withdraw_rewards(&user, &[ls_id(0)]).unwrap();
+    assert!(!token_rewards(&suite, &ls_id(0)).is_zero());
+}
+
+/// Rewrites the portal, token and delegator of `id` to the state 0.1.x left them in
+fn downgrade_to_v0_1(suite: &mut Suite, id: &PositionId) {
+    let data = suite.data(id);
+    let portal = suite.portal.clone();
+    let legacy_env = v0_1::PortalEnv {
+        cw20_code_id: suite.code_ids.cw20,
+        cw721_address: suite.cw721.to_string(),
+        delegator_code_id: suite.code_ids.delegator,
+    };
+    suite.with_contract_storage(&portal, |storage| {
+        positions().remove(storage, id.key()).unwrap();
+        let record = v0_1::LiquidStakingData {
+            token_address: data.token_address.clone(),
+            delegator_address: data.delegator_address.clone(),
+            validator: None,
+        };
+        v0_1::LS_DATA.save(storage, id.key(), &record).unwrap();
+        POSITION_SEQ.remove(storage, &id.validator);
+        v0_1::PORTAL_ENV.save(storage, &legacy_env).unwrap();
+        cw2::set_contract_version(storage, "crates.io:portal", "0.1.0").unwrap();
+    });
+    // tokens had no minter and no rewards
+    suite.with_contract_storage(&Addr::unchecked(&data.token_address), |storage| {
+        cw20_base::state::REWARD_INFO.remove(storage);
+        cw20_base::state::TOKEN_INFO
+            .update(storage, |mut info| -> StdResult<_> {
+                info.mint = None;
+                Ok(info)
+            })
+            .unwrap();
+        cw2::set_contract_version(storage, "crates.io:cw20-base", "0.1.0").unwrap();
+    });
+    // delegators did not store their validator
+    suite.with_contract_storage(&Addr::unchecked(&data.delegator_address), |storage| {
+        delegator::state::VALIDATOR.remove(storage);
+        cw2::set_contract_version(storage, "crates.io:delegator", "0.1.0").unwrap();
+    });
+    // records were minted with an empty extension
+    let cw721 = suite.cw721.clone();
+    suite.with_contract_storage(&cw721, |storage| {
+        let token_id = id.to_string();
+        let tokens =
+            cw721_base::Cw721Contract::<cw721_base::Extension, Empty, Empty, Empty>::default()
+                .tokens;
+        let token = tokens.load(storage, &token_id).unwrap();
+        tokens.remove(storage, &token_id).unwrap();
+        let legacy_tokens =
+            cw721_base::Cw721Contract::<Option<Empty>, Empty, Empty, Empty>::default().tokens;
+        let record = cw721_base::state::TokenInfo {
+            owner: token.owner,
+            approvals: token.approvals,
+            token_uri: None,
+            extension: Some(Empty {}),
+        };
+        legacy_tokens.save(storage, &token_id, &record).unwrap();
+    });
+}
+
+/// Moves the record cw721 back to the code 0.1.x deployed, the portal being its admin
+fn downgrade_cw721_to_v0_1(suite: &mut Suite) {
+    let legacy_code_id = suite.app.store_code(legacy_cw721_contract());
+    suite
+        .app
+        .migrate_contract(
+            suite.portal.clone(),
+            suite.cw721.clone(),
+            &Empty {},
+            legacy_code_id,
+        )
+        .unwrap();
+    let cw721 = suite.cw721.clone();
+    suite.with_contract_storage(&cw721, |storage| {
+        cw2::set_contract_version(storage, "crates.io:cw721-base", "0.1.0").unwrap();
+    });
+}
+
+#[test]
+fn migrate_from_v0_1() {
+    let mut suite = Suite::new();
+    let owner = suite.owner.clone();
+    let user = suite.user.clone();
+    for _ in 0..2 {
+        suite
+            .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+            .unwrap();
+    }
+    for id in [ls_id(0), ls_id(1)] {
+        downgrade_to_v0_1(&mut suite, &id);
+    }
+    downgrade_cw721_to_v0_1(&mut suite);
+
+    // the cw721 of 0.1.x cannot hold the record metadata, so it moves along
+    let err = suite
+        .app
+        .migrate_contract(
+            owner.clone(),
+            suite.portal.clone(),
+            &MigrateMsg {
+                cw721_code_id: None,
+            },
+            suite.code_ids.portal,
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::MissingCw721CodeId {}
+    );
+    let res = suite
+        .app
+        .migrate_contract(
+            owner.clone(),
+            suite.portal.clone(),
+            &MigrateMsg {
+                cw721_code_id: Some(suite.code_ids.cw721),
+            },
+            suite.code_ids.portal,
+        )
+        .unwrap();
+    assert_eq!(attribute(&res, "from_version"), Some("0.1.0".to_string()));
+    let cw721 = suite
+        .app
+        .wrap()
+        .query_wasm_contract_info(&suite.cw721)
+        .unwrap();
+    assert_eq!(cw721.code_id, suite.code_ids.cw721);
+    let version = cw2::CONTRACT
+        .query(&suite.app.wrap(), suite.cw721.clone())
+        .unwrap();
+    assert_eq!(version.version, "0.2.0");
+    // legacy records read as the default metadata until their position is migrated
+    assert_eq!(
+        suite.nft_info(&ls_id(0)).extension,
+        Some(cw721_base::Metadata::default())
+    );
+    let ownership: Ownership<Addr> = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.portal, &QueryMsg::Ownership {})
+        .unwrap();
+    assert_eq!(ownership.owner, Some(owner.clone()));
+
+    // new positions continue after the legacy ids, which are not moved yet
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    assert_eq!(suite.nft_owner(&ls_id(2)), user.to_string());
+
+    let cw20_code_id = suite.code_ids.cw20;
+    let delegator_code_id = suite.code_ids.delegator;
+    let migrate = move |limit| ExecuteMsg::MigrateLegacyPositions {
+        cw20_code_id,
+        delegator_code_id,
+        limit,
+    };
+    let err = suite
+        .app
+        .execute_contract(user.clone(), suite.portal.clone(), &migrate(None), &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Ownership(OwnershipError::NotOwner)
+    );
+
+    // in batches, skipping the positions created since
+    let res = suite
+        .app
+        .execute_contract(owner.clone(), suite.portal.clone(), &migrate(Some(1)), &[])
+        .unwrap();
+    assert_eq!(attribute(&res, "id"), Some(ls_id(0).to_string()));
+    assert_eq!(
+        attribute(&res, "next_start_after"),
+        Some(ls_id(0).to_string())
+    );
+    let res = suite
+        .app
+        .execute_contract(owner.clone(), suite.portal.clone(), &migrate(None), &[])
+        .unwrap();
+    assert_eq!(attribute(&res, "id"), Some(ls_id(1).to_string()));
+    assert_eq!(attribute(&res, "next_start_after"), None);
+    let err = suite
+        .app
+        .execute_contract(owner, suite.portal.clone(), &migrate(None), &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::NothingToMigrate {}
+    );
+    assert_eq!(
+        positions_by_validator(&suite, VALIDATOR),
+        vec![ls_id(0), ls_id(1), ls_id(2)]
+    );
+
+    // the records are rebuilt from the positions
+    let data = suite.data(&ls_id(0));
+    let token_info: cw20::TokenInfoResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&data.token_address, &cw20_base::msg::QueryMsg::TokenInfo {})
+        .unwrap();
+    let info = suite.nft_info(&ls_id(0));
+    let record = info.extension.unwrap();
+    assert_eq!(
+        record,
+        cw721_base::Metadata {
+            name: token_info.name,
+            description: None,
+            validator: VALIDATOR.to_string(),
+            initial_amount: coin(0, DENOM),
+            delegated: coin(1_000_000, DENOM),
+            token_address: data.token_address.clone(),
+            delegator_address: data.delegator_address.clone(),
+            created_height: 0,
+            created_time: Default::default(),
+        }
+    );
+    assert_eq!(info.token_uri, Some(record_token_uri(&record).unwrap()));
+
+    // the delegator recovered its validator from the delegation
+    let config: delegator::msg::ConfigResponse =
+        suite.query_delegator(&ls_id(0), &delegator::msg::QueryMsg::Config {});
+    assert_eq!(config.validator, VALIDATOR);
+
+    // rewards of the legacy position are shared by its token holders
+    suite.advance_time(YEAR);
+    suite.withdraw_rewards(&user, &[ls_id(0)]).unwrap();
+    assert_eq!(token_rewards(&suite, &ls_id(0)), Uint128::new(100_000));
+    let before = suite.balance(&user);
+    suite.claim_rewards(&user, &ls_id(0)).unwrap();
+    assert_eq!(suite.balance(&user) - before, Uint128::new(100_000));
+
+    // the portal mints top-ups
+    suite
+        .app
+        .execute_contract(
+            user.clone(),
+            suite.portal.clone(),
+            &ExecuteMsg::Delegate { id: ls_id(0) },
+            &coins(500_000, DENOM),
+        )
+        .unwrap();
+    assert_eq!(
+        suite.cw20_balance(&data.token_address, &user),
+        Uint128::new(1_500_000)
+    );
+    let record = suite.nft_info(&ls_id(0)).extension.unwrap();
+    assert_eq!(record.delegated, coin(1_500_000, DENOM));
+
+    // and burns what is undelegated
+    suite.undelegate(&user, &ls_id(0), 1_500_000).unwrap();
+    assert_eq!(
+        suite.cw20_balance(&data.token_address, &user),
+        Uint128::zero()
+    );
+    let delegated = suite
+        .delegation(&data.delegator_address)
+        .map(|delegation| delegation.amount.amount)
+        .unwrap_or_default();
+    assert_eq!(delegated, Uint128::zero());
+}
+
+#[test]
+fn paginated_position_queries() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+    let created_at = suite.app.block_info().height;
+    for _ in 0..3 {
+        suite
+            .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+            .unwrap();
+    }
+    suite
+        .delegate_and_tokenize(&user, OTHER_VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    let other_id = PositionId::new(OTHER_VALIDATOR, 0);
+
+    let query_ids = |suite: &Suite, msg: &QueryMsg| -> Vec<PositionId> {
+        let res: AllResponse = suite
+            .app
+            .wrap()
+            .query_wasm_smart(&suite.portal, msg)
+            .unwrap();
+        res.data
+    };
+
+    // ids are in key order, pages continue after the last id
+    let page = query_ids(
+        &suite,
+        &QueryMsg::All {
+            start_after: None,
+            limit: Some(2),
+        },
+    );
+    assert_eq!(page, vec![other_id.clone(), ls_id(0)]);
+    let page = query_ids(
+        &suite,
+        &QueryMsg::All {
+            start_after: Some(ls_id(0)),
+            limit: Some(2),
+        },
+    );
+    assert_eq!(page, vec![ls_id(1), ls_id(2)]);
+
+    let page = query_ids(
+        &suite,
+        &QueryMsg::ByValidator {
+            validator: VALIDATOR.to_string(),
+            start_after: Some(ls_id(0)),
+            limit: None,
+        },
+    );
+    assert_eq!(page, vec![ls_id(1), ls_id(2)]);
+    let page = query_ids(
+        &suite,
+        &QueryMsg::ByValidator {
+            validator: OTHER_VALIDATOR.to_string(),
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(page, vec![other_id]);
+
+    suite.advance_time(YEAR);
+    let position: PositionResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.portal, &QueryMsg::Position { id: ls_id(1) })
+        .unwrap();
+    assert_eq!(position.id, ls_id(1));
+    assert_eq!(position.validator, VALIDATOR);
+    assert_eq!(position.owner, Some(user.to_string()));
+    assert_eq!(position.token_address, suite.data(&ls_id(1)).token_address);
+    assert_eq!(position.delegated, Uint128::new(1_000_000));
+    assert_eq!(position.supply, Uint128::new(1_000_000));
+    assert_eq!(position.created_at, created_at);
+    assert_eq!(position.pending_rewards.len(), 1);
+    assert_eq!(position.pending_rewards[0].denom, DENOM);
+    assert!(!position.pending_rewards[0].amount.is_zero());
+}
+
+#[test]
+fn delegator_queries() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    let config: delegator::msg::ConfigResponse =
+        suite.query_delegator(&ls_id(0), &delegator::msg::QueryMsg::Config {});
+    assert_eq!(config.owner, Some(suite.portal.to_string()));
+    assert_eq!(config.validator, VALIDATOR);
+    let delegation: delegator::msg::DelegationResponse =
+        suite.query_delegator(&ls_id(0), &delegator::msg::QueryMsg::Delegation {});
+    assert_eq!(delegation.amount, coin(1_000_000, DENOM));
+
+    suite.advance_time(YEAR);
+    let pending: delegator::msg::PendingRewardsResponse =
+        suite.query_delegator(&ls_id(0), &delegator::msg::QueryMsg::PendingRewards {});
+    assert_eq!(pending.rewards.len(), 1);
+    assert!(!pending.rewards[0].amount.is_zero());
+
+    suite.undelegate(&user, &ls_id(0), 400_000).unwrap();
+    let unbondings: delegator::msg::UnbondingsResponse =
+        suite.query_delegator(&ls_id(0), &delegator::msg::QueryMsg::Unbondings {});
+    assert_eq!(
+        unbondings.unbondings,
+        vec![delegator::msg::UnbondingInfo {
+            id: 0,
+            initial_amount: Uint128::new(400_000),
+            amount: Uint128::new(400_000),
+            start_time: suite.app.block_info().time,
+            completion_time: suite.app.block_info().time.plus_seconds(UNBONDING_TIME),
+            released: false,
+        }]
+    );
+
+    // unbonded coins stay idle until claimed
+    suite.advance_time(UNBONDING_TIME);
+    suite.process_unbondings();
+    let unbondings: delegator::msg::UnbondingsResponse =
+        suite.query_delegator(&ls_id(0), &delegator::msg::QueryMsg::Unbondings {});
+    assert!(unbondings.unbondings[0].released);
+    let idle: delegator::msg::IdleBalanceResponse =
+        suite.query_delegator(&ls_id(0), &delegator::msg::QueryMsg::IdleBalance {});
+    assert_eq!(idle.balance, coin(400_000, DENOM));
+
+    suite.claim(&user).unwrap();
+    let unbondings: delegator::msg::UnbondingsResponse =
+        suite.query_delegator(&ls_id(0), &delegator::msg::QueryMsg::Unbondings {});
+    assert!(unbondings.unbondings.is_empty());
+    let idle: delegator::msg::IdleBalanceResponse =
+        suite.query_delegator(&ls_id(0), &delegator::msg::QueryMsg::IdleBalance {});
+    assert_eq!(idle.balance, coin(0, DENOM));
+}
+
+#[test]
+fn malformed_position_id_is_rejected() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+
+    let msg = br#"{"undelegate":{"id":"linkvaloper1validator","amount":"1"}}"#;
+    let err = suite
+        .app
+        .execute(
+            user,
+            WasmMsg::Execute {
+                contract_addr: suite.portal.to_string(),
+                msg: Binary::from(msg.to_vec()),
+                funds: vec![],
+            }
+            .into(),
+        )
+        .unwrap_err();
+    assert!(err
+        .chain()
+        .any(|cause| cause.to_string().contains("Invalid position id")));
+}
+
+#[test]
+fn position_by_token_and_delegator() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+    for _ in 0..2 {
+        suite
+            .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+            .unwrap();
+    }
+    let data = suite.data(&ls_id(1));
+
+    let position: PositionResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.portal,
+            &QueryMsg::PositionByToken {
+                address: data.token_address.clone(),
+            },
+        )
+        .unwrap();
+    assert_eq!(position.id, ls_id(1));
+    assert_eq!(position.delegator_address, data.delegator_address);
+
+    let position: PositionResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.portal,
+            &QueryMsg::PositionByDelegator {
+                address: data.delegator_address.clone(),
+            },
+        )
+        .unwrap();
+    assert_eq!(position.id, ls_id(1));
+    assert_eq!(position.token_address, data.token_address);
+
+    // addresses that belong to no position are not found
+    suite
+        .app
+        .wrap()
+        .query_wasm_smart::<PositionResponse>(
+            &suite.portal,
+            &QueryMsg::PositionByToken {
+                address: user.to_string(),
+            },
+        )
+        .unwrap_err();
+}
+
+#[test]
+fn undelegate_with_send() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    let data = suite.data(&ls_id(0));
+    suite.advance_time(YEAR);
+
+    // a single Send burns the tokens and undelegates, no allowance needed
+    let before = suite.balance(&user);
+    let send = cw20_base::msg::ExecuteMsg::Send {
+        contract: suite.portal.to_string(),
+        amount: Uint128::new(400_000),
+        msg: to_json_binary(&ReceiveMsg::Undelegate {}).unwrap(),
+    };
+    let res = suite
+        .app
+        .execute_contract(
+            user.clone(),
+            Addr::unchecked(&data.token_address),
+            &send,
+            &[],
+        )
+        .unwrap();
+    assert_eq!(
+        suite.cw20_balance(&data.token_address, &user),
+        Uint128::new(600_000)
+    );
+    assert_eq!(
+        suite.cw20_balance(&data.token_address, &suite.portal),
+        Uint128::zero()
+    );
+    let delegation = suite.delegation(&data.delegator_address).unwrap();
+    assert_eq!(delegation.amount.amount, Uint128::new(600_000));
+    let claims: ClaimsResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.portal,
+            &QueryMsg::Claims {
+                address: user.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(claims.claims.len(), 1);
+    assert_eq!(claims.claims[0].amount, Uint128::new(400_000));
+
+    // the rewards harvested on the way are not left with the portal
+    assert_eq!(
+        attribute(&res, "forwarded_rewards"),
+        Some("40000".to_string())
+    );
+    assert_eq!(suite.balance(&user) - before, Uint128::new(40_000));
+    assert_eq!(suite.balance(&suite.portal), Uint128::zero());
+    suite.claim_rewards(&user, &ls_id(0)).unwrap();
+    assert_eq!(suite.balance(&user) - before, Uint128::new(100_000));
+
+    // only position tokens are accepted
+    let err = suite
+        .app
+        .execute_contract(
+            user.clone(),
+            suite.portal.clone(),
+            &ExecuteMsg::Receive(Cw20ReceiveMsg {
+                sender: user.to_string(),
+                amount: Uint128::new(1),
+                msg: to_json_binary(&ReceiveMsg::Undelegate {}).unwrap(),
+            }),
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::UnknownPositionToken {
+            address: user.to_string()
+        }
+    );
+}
+
+#[test]
+fn close_position() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+    let other = suite.app.api().addr_make("other");
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    let data = suite.data(&ls_id(0));
+    let send_nft =
+        cw721_base::ExecuteMsg::<cw721_base::Extension, cw721_base::ExtensionMsg>::SendNft {
+            contract: suite.portal.to_string(),
+            token_id: ls_id(0).to_string(),
+            msg: to_json_binary(&NftReceiveMsg::Close {}).unwrap(),
+        };
+
+    // every token is needed to close the position
+    suite
+        .transfer_tokens(&user, &ls_id(0), &other, 100_000)
+        .unwrap();
+    let err = suite
+        .app
+        .execute_contract(user.clone(), suite.cw721.clone(), &send_nft, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::PositionNotFullyOwned {
+            balance: Uint128::new(900_000),
+            supply: Uint128::new(1_000_000),
+        }
+    );
+    assert_eq!(suite.nft_owner(&ls_id(0)), user.to_string());
+
+    suite
+        .transfer_tokens(&other, &ls_id(0), &user, 100_000)
+        .unwrap();
+    suite
+        .app
+        .execute_contract(
+            user.clone(),
+            Addr::unchecked(&data.token_address),
+            &cw20_base::msg::ExecuteMsg::IncreaseAllowance {
+                spender: suite.portal.to_string(),
+                amount: Uint128::new(1_000_000),
+                expires: None,
+            },
+            &[],
+        )
+        .unwrap();
+    suite
+        .app
+        .execute_contract(user.clone(), suite.cw721.clone(), &send_nft, &[])
+        .unwrap();
+
+    assert_eq!(
+        suite.cw20_balance(&data.token_address, &user),
+        Uint128::zero()
+    );
+    assert!(suite.delegation(&data.delegator_address).is_none());
+    let position: PositionResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.portal, &QueryMsg::Position { id: ls_id(0) })
+        .unwrap();
+    assert_eq!(position.owner, None);
+    assert_eq!(position.supply, Uint128::zero());
+    assert_eq!(position.closed_at, Some(suite.app.block_info().height));
+    let err = suite
+        .app
+        .execute_contract(
+            user.clone(),
+            suite.portal.clone(),
+            &ExecuteMsg::Delegate { id: ls_id(0) },
+            &coins(1_000_000, DENOM),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::PositionClosed {
+            id: ls_id(0).to_string()
+        }
+    );
+
+    // the whole stake is claimable once unbonded
+    suite.advance_time(UNBONDING_TIME);
+    suite.process_unbondings();
+    let before = suite.balance(&user);
+    suite.claim(&user).unwrap();
+    assert_eq!(suite.balance(&user) - before, Uint128::new(1_000_000));
+}
+
+#[test]
+fn token_metadata() {
+    let mut suite = Suite::new();
+    let owner = suite.owner.clone();
+    let user = suite.user.clone();
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+
+    // named after the validator and the position by default
+    let data = suite.data(&ls_id(0));
+    let token_info: cw20::TokenInfoResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&data.token_address, &cw20_base::msg::QueryMsg::TokenInfo {})
+        .unwrap();
+    assert_eq!(token_info.name, format!("{MONIKER} Liquid Staking #0"));
+    assert_eq!(token_info.symbol, format!("ls{MONIKER}"));
+    let marketing: cw20::MarketingInfoResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &data.token_address,
+            &cw20_base::msg::QueryMsg::MarketingInfo {},
+        )
+        .unwrap();
+    assert_eq!(
+        marketing.description,
+        Some(format!(
+            "Liquid staking position {}, delegated to {MONIKER} ({VALIDATOR})",
+            ls_id(0)
+        ))
+    );
+    assert_eq!(marketing.logo, None);
+
+    let update = ExecuteMsg::UpdateTokenMetadata {
+        name_template: "Staked with {moniker} {id}".to_string(),
+        symbol_template: "st-{moniker}".to_string(),
+        description_template: None,
+        logo_url: Some("https://example.com/logo.png".to_string()),
+    };
+    let err = suite
+        .app
+        .execute_contract(user.clone(), suite.portal.clone(), &update, &[])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<ContractError>().unwrap(),
+        ContractError::Ownership(OwnershipError::NotOwner)
+    );
+    suite
+        .app
+        .execute_contract(owner, suite.portal.clone(), &update, &[])
+        .unwrap();
+    let metadata: TokenMetadataResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&suite.portal, &QueryMsg::TokenMetadata {})
+        .unwrap();
+    assert_eq!(metadata.symbol_template, "st-{moniker}".to_string());
+
+    // only positions created afterwards use the new templates
+    suite
+        .delegate_and_tokenize(&user, OTHER_VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    let id = PositionId::new(OTHER_VALIDATOR, 0);
+    let data = suite.data(&id);
+    let token_info: cw20::TokenInfoResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&data.token_address, &cw20_base::msg::QueryMsg::TokenInfo {})
+        .unwrap();
+    assert_eq!(
+        token_info.name,
+        format!("Staked with {OTHER_MONIKER} {OTHER_VALIDATOR}/0")
+    );
+    assert_eq!(token_info.symbol, format!("st-{OTHER_MONIKER}"));
+    let marketing: cw20::MarketingInfoResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &data.token_address,
+            &cw20_base::msg::QueryMsg::MarketingInfo {},
+        )
+        .unwrap();
+    assert_eq!(marketing.description, None);
+    assert_eq!(
+        marketing.logo,
+        Some(cw20::LogoInfo::Url(
+            "https://example.com/logo.png".to_string()
+        ))
+    );
+    let token_info: cw20::TokenInfoResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(
+            &suite.data(&ls_id(0)).token_address,
+            &cw20_base::msg::QueryMsg::TokenInfo {},
+        )
+        .unwrap();
+    assert_eq!(token_info.symbol, format!("ls{MONIKER}"));
+}
+
+#[test]
+fn token_metadata_without_moniker() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+
+    // the validator reports no description, the defaults apply
+    suite
+        .delegate_and_tokenize(&user, ANONYMOUS_VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    let id = PositionId::new(ANONYMOUS_VALIDATOR, 0);
+    let data = suite.data(&id);
+    let token_info: cw20::TokenInfoResponse = suite
+        .app
+        .wrap()
+        .query_wasm_smart(&data.token_address, &cw20_base::msg::QueryMsg::TokenInfo {})
+        .unwrap();
+    assert_eq!(token_info.name, DEFAULT_NAME.to_string());
+    assert_eq!(token_info.symbol, DEFAULT_SYMBOL.to_string());
+}
+
+#[test]
+fn record_metadata() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    let data = suite.data(&ls_id(0));
+    let block = suite.app.block_info();
+
+    let info = suite.nft_info(&ls_id(0));
+    let record = cw721_base::Metadata {
+        name: format!("{MONIKER} Liquid Staking #0"),
+        description: Some(format!(
+            "Liquid staking position {}, delegated to {MONIKER} ({VALIDATOR})",
+            ls_id(0)
+        )),
+        validator: VALIDATOR.to_string(),
+        initial_amount: coin(1_000_000, DENOM),
+        delegated: coin(1_000_000, DENOM),
+        token_address: data.token_address,
+        delegator_address: data.delegator_address,
+        created_height: block.height,
+        created_time: block.time,
+    };
+    assert_eq!(info.token_uri, Some(record_token_uri(&record).unwrap()));
+    assert_eq!(info.extension, Some(record));
+}
+
+#[test]
+fn record_metadata_follows_stake() {
+    let mut suite = Suite::new();
+    let user = suite.user.clone();
+    let holder = suite.app.api().addr_make("holder");
+    suite
+        .delegate_and_tokenize(&user, VALIDATOR, &coins(1_000_000, DENOM))
+        .unwrap();
+    let minted = suite.nft_info(&ls_id(0));
+
+    suite
+        .app
+        .execute_contract(
+            user.clone(),
+            suite.portal.clone(),
+            &ExecuteMsg::Delegate { id: ls_id(0) },
+            &coins(1_500_000, DENOM),
+        )
+        .unwrap();
+    let info = suite.nft_info(&ls_id(0));
+    let record = info.extension.unwrap();
+    assert_eq!(record.delegated, coin(2_500_000, DENOM));
+    // the rest of the record keeps describing how the position was created
+    assert_eq!(
+        record,
+        cw721_base::Metadata {
+            delegated: coin(2_500_000, DENOM),
+            ..minted.extension.unwrap()
+        }
+    );
+    assert_eq!(info.token_uri, Some(record_token_uri(&record).unwrap()));
+    assert_ne!(info.token_uri, minted.token_uri);
+
+    suite.undelegate(&user, &ls_id(0), 2_000_000).unwrap();
+    let info = suite.nft_info(&ls_id(0));
+    let record = info.extension.unwrap();
+    assert_eq!(record.delegated, coin(500_000, DENOM));
+    assert_eq!(info.token_uri, Some(record_token_uri(&record).unwrap()));
+
+    // holders keep undelegating after the owner burned the record through the cw721
+    suite
+        .transfer_tokens(&user, &ls_id(0), &holder, 100_000)
+        .unwrap();
+    suite
+        .app
+        .execute_contract(
+            user.clone(),
+            suite.cw721.clone(),
+            &cw721_base::ExecuteMsg::<cw721_base::Extension, cw721_base::ExtensionMsg>::Burn {
+                token_id: ls_id(0).to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+    suite.undelegate(&holder, &ls_id(0), 100_000).unwrap();
+
+    // only the portal updates the record
+    let err = suite
+        .app
+        .execute_contract(
+            user.clone(),
+            suite.cw721.clone(),
+            &cw721_base::ExecuteMsg::<cw721_base::Extension, cw721_base::ExtensionMsg>::Extension {
+                msg: cw721_base::ExtensionMsg::UpdateMetadata {
+                    token_id: ls_id(0).to_string(),
+                    token_uri: None,
+                    extension: None,
+                },
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast::<cw721_base::ContractError>().unwrap(),
+        cw721_base::ContractError::Ownership(OwnershipError::NotOwner)
+    );
+}
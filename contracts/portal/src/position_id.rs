@@ -0,0 +1,130 @@
+use std::fmt;
+use std::str::FromStr;
+
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::ContractError;
+
+/// Identifies a position as `<validator>/<sequence>`, which is also the token id of its record.
+/// Serialized as that string.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PositionId {
+    /// Validator the position was created with
+    pub validator: String,
+    /// Number of positions created with the validator before this one
+    pub seq: u32,
+}
+
+impl PositionId {
+    pub fn new(validator: impl Into<String>, seq: u32) -> Self {
+        PositionId {
+            validator: validator.into(),
+            seq,
+        }
+    }
+
+    /// Key of the position in `positions`
+    pub fn key(&self) -> (&str, u32) {
+        (&self.validator, self.seq)
+    }
+}
+
+impl fmt::Display for PositionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.validator, self.seq)
+    }
+}
+
+impl FromStr for PositionId {
+    type Err = ContractError;
+
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        let invalid = || ContractError::InvalidPositionId { id: id.to_string() };
+        let (validator, seq) = id.split_once('/').ok_or_else(invalid)?;
+        // reject signs and padding, so that every position has a single id
+        if validator.is_empty() || seq.is_empty() || !seq.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        if seq.len() > 1 && seq.starts_with('0') {
+            return Err(invalid());
+        }
+        let seq = seq.parse().map_err(|_| invalid())?;
+        Ok(PositionId::new(validator, seq))
+    }
+}
+
+impl From<PositionId> for String {
+    fn from(id: PositionId) -> Self {
+        id.to_string()
+    }
+}
+
+impl Serialize for PositionId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for PositionId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        id.parse().map_err(de::Error::custom)
+    }
+}
+
+impl JsonSchema for PositionId {
+    fn schema_name() -> String {
+        "PositionId".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        String::json_schema(gen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{from_json, to_json_binary};
+
+    use super::*;
+
+    #[test]
+    fn parse_and_display() {
+        let id: PositionId = "linkvaloper1validator/12".parse().unwrap();
+        assert_eq!(id, PositionId::new("linkvaloper1validator", 12));
+        assert_eq!(id.to_string(), "linkvaloper1validator/12");
+        assert_eq!(id.key(), ("linkvaloper1validator", 12));
+    }
+
+    #[test]
+    fn reject_malformed_ids() {
+        for id in [
+            "",
+            "linkvaloper1validator",
+            "linkvaloper1validator/",
+            "/1",
+            "linkvaloper1validator/-1",
+            "linkvaloper1validator/+1",
+            "linkvaloper1validator/01",
+            "linkvaloper1validator/1/2",
+            "linkvaloper1validator/4294967296",
+        ] {
+            assert_eq!(
+                id.parse::<PositionId>().unwrap_err(),
+                ContractError::InvalidPositionId { id: id.to_string() }
+            );
+        }
+    }
+
+    #[test]
+    fn serde_as_string() {
+        let id = PositionId::new("linkvaloper1validator", 3);
+        let json = to_json_binary(&id).unwrap();
+        assert_eq!(json.as_slice(), br#""linkvaloper1validator/3""#);
+        assert_eq!(from_json::<PositionId>(&json).unwrap(), id);
+        assert!(from_json::<PositionId>(br#""linkvaloper1validator/x""#).is_err());
+    }
+}
@@ -32,6 +32,57 @@ pub struct CodeInfoResponse {
     pub data_hash: ::prost::alloc::vec::Vec<u8>,
 }
 
+#[derive(Clone, PartialEq, Message)]
+pub struct QueryParamsRequest {}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct QueryParamsResponse {
+    #[prost(message, optional, tag = "1")]
+    pub params: ::core::option::Option<Params>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Params {
+    #[prost(message, optional, tag = "1")]
+    pub unbonding_time: ::core::option::Option<Duration>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Duration {
+    #[prost(int64, tag = "1")]
+    pub seconds: i64,
+    #[prost(int32, tag = "2")]
+    pub nanos: i32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct QueryValidatorRequest {
+    #[prost(string, tag = "1")]
+    pub validator_addr: ::prost::alloc::string::String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct QueryValidatorResponse {
+    #[prost(message, optional, tag = "1")]
+    pub validator: ::core::option::Option<ValidatorInfo>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ValidatorInfo {
+    #[prost(bool, tag = "3")]
+    pub jailed: bool,
+    #[prost(int32, tag = "4")]
+    pub status: i32,
+    #[prost(message, optional, tag = "7")]
+    pub description: ::core::option::Option<Description>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Description {
+    #[prost(string, tag = "1")]
+    pub moniker: ::prost::alloc::string::String,
+}
+
 pub fn query_wasm_code_hash(querier: QuerierWrapper, code_id: u64) -> StdResult<Vec<u8>> {
     let value = query(
         querier,
@@ -57,6 +108,63 @@ pub fn query_wasm_code_hash(querier: QuerierWrapper, code_id: u64) -> StdResult<
     }
 }
 
+pub fn query_unbonding_time(querier: QuerierWrapper) -> StdResult<u64> {
+    let value = query(
+        querier,
+        &QueryRequest::Custom(CustomQuery {
+            path: "/cosmos.staking.v1beta1.Query/Params".to_string(),
+            data: QueryParamsRequest {}.encode_to_vec().into(),
+        }),
+    );
+
+    let res = match QueryParamsResponse::decode(&*value?.to_vec()) {
+        Ok(res) => Ok(res),
+        Err(err) => Err(StdError::GenericErr {
+            msg: err.to_string(),
+        }),
+    };
+
+    if let Some(unbonding_time) = res?.params.and_then(|params| params.unbonding_time) {
+        Ok(unbonding_time.seconds as u64)
+    } else {
+        Err(StdError::GenericErr {
+            msg: "unbonding time is empty".to_string(),
+        })
+    }
+}
+
+/// `cosmos.staking.v1beta1.BondStatus` of a validator in the active set
+pub const BOND_STATUS_BONDED: i32 = 3;
+
+pub fn query_validator_info(querier: QuerierWrapper, validator: &str) -> StdResult<ValidatorInfo> {
+    let value = query(
+        querier,
+        &QueryRequest::Custom(CustomQuery {
+            path: "/cosmos.staking.v1beta1.Query/Validator".to_string(),
+            data: QueryValidatorRequest {
+                validator_addr: validator.to_string(),
+            }
+            .encode_to_vec()
+            .into(),
+        }),
+    );
+
+    let res = match QueryValidatorResponse::decode(&*value?.to_vec()) {
+        Ok(res) => Ok(res),
+        Err(err) => Err(StdError::GenericErr {
+            msg: err.to_string(),
+        }),
+    };
+
+    if let Some(validator) = res?.validator {
+        Ok(validator)
+    } else {
+        Err(StdError::GenericErr {
+            msg: "validator is empty".to_string(),
+        })
+    }
+}
+
 pub fn query(querier: QuerierWrapper, request: &QueryRequest<CustomQuery>) -> StdResult<Binary> {
     let raw = to_json_vec(request).map_err(|serialize_err| {
         StdError::generic_err(format!("Serializing QueryRequest: {serialize_err}"))
@@ -1,20 +1,132 @@
+use cosmwasm_std::{Addr, Empty, Timestamp, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cw_storage_plus::{Item, Map};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex, UniqueIndex};
+
+use crate::position_id::PositionId;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct PortalEnv {
     pub cw20_code_id: u64,
     pub cw721_address: String,
     pub delegator_code_id: u64,
+    pub min_delegation: Uint128,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct LiquidStakingData {
     pub token_address: String,
     pub delegator_address: String,
+    /// Current validator, which differs from the one in the id after a redelegation
+    pub validator: String,
+    /// Block height the position was created at, 0 for positions created before 0.2.0
+    pub created_at: u64,
+}
+
+/// Commission taken from every reward withdrawal of every position
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct FeeConfig {
+    pub fee_bps: u16,
+    pub treasury: Addr,
+}
+
+/// Stake undelegated by a user, claimable from the delegator once unbonding completes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Unbonding {
+    pub id: PositionId,
+    pub delegator_address: String,
+    pub amount: Uint128,
+    pub completion_time: Timestamp,
+}
+
+/// Rewards the portal is credited while burning tokens sent to it, forwarded by the reply
+/// to the burn
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PendingForward {
+    pub token_address: String,
+    pub recipient: Addr,
+}
+
+/// Templates of the name, symbol and marketing info of the tokens of new positions,
+/// see `crate::metadata`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct TokenMetadata {
+    pub name_template: String,
+    pub symbol_template: String,
+    pub description_template: Option<String>,
+    pub logo_url: Option<String>,
 }
 
 pub const PORTAL_ENV: Item<PortalEnv> = Item::new("portal_env");
-pub const LS_DATA: Map<(&str, u32), LiquidStakingData> = Map::new("ls_data");
+/// Next sequence of the position ids of each validator, never reused
+pub const POSITION_SEQ: Map<&str, u32> = Map::new("position_seq");
+/// Block height each closed position was closed at, keyed like `positions`
+pub const CLOSED_POSITIONS: Map<(&str, u32), u64> = Map::new("closed_positions");
+/// Addresses predicted by `DelegateAndTokenize`, checked against the instantiate replies
+pub const PENDING_LS_DATA: Item<LiquidStakingData> = Item::new("pending_ls_data");
+pub const PENDING_FORWARD: Item<PendingForward> = Item::new("pending_forward");
+pub const VALIDATOR_ALLOW_LIST_ENABLED: Item<bool> = Item::new("validator_allow_list_enabled");
+pub const VALIDATOR_ALLOW_LIST: Map<&str, Empty> = Map::new("validator_allow_list");
+pub const UNBONDING_SEQ: Item<u64> = Item::new("unbonding_seq");
+pub const UNBONDINGS: Map<(&Addr, u64), Unbonding> = Map::new("unbondings");
+/// Unset until the owner configures a fee
+pub const FEE_CONFIG: Item<FeeConfig> = Item::new("fee_config");
+/// Unset until the owner configures templates, the defaults apply meanwhile
+pub const TOKEN_METADATA: Item<TokenMetadata> = Item::new("token_metadata");
+
+pub struct PositionIndexes<'a> {
+    /// Current validator
+    pub validator: MultiIndex<'a, String, LiquidStakingData, (&'a str, u32)>,
+    pub token: UniqueIndex<'a, String, LiquidStakingData, (&'a str, u32)>,
+    pub delegator: UniqueIndex<'a, String, LiquidStakingData, (&'a str, u32)>,
+}
+
+impl<'a> IndexList<LiquidStakingData> for PositionIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<LiquidStakingData>> + '_> {
+        let v: Vec<&dyn Index<LiquidStakingData>> =
+            vec![&self.validator, &self.token, &self.delegator];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Positions keyed by `PositionId::key`
+pub fn positions<'a>() -> IndexedMap<'a, (&'a str, u32), LiquidStakingData, PositionIndexes<'a>> {
+    let indexes = PositionIndexes {
+        validator: MultiIndex::new(
+            |_pk, data| data.validator.clone(),
+            "ls_data",
+            "ls_data__validator",
+        ),
+        token: UniqueIndex::new(|data| data.token_address.clone(), "ls_data__token"),
+        delegator: UniqueIndex::new(|data| data.delegator_address.clone(), "ls_data__delegator"),
+    };
+    IndexedMap::new("ls_data", indexes)
+}
+
+/// State layout of 0.1.x, read once by `migrate`
+pub mod v0_1 {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+    pub struct PortalEnv {
+        pub cw20_code_id: u64,
+        pub cw721_address: String,
+        pub delegator_code_id: u64,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+    pub struct LiquidStakingData {
+        pub token_address: String,
+        pub delegator_address: String,
+        /// Only found on positions already in the current layout
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub validator: Option<String>,
+    }
+
+    pub const PORTAL_ENV: Item<PortalEnv> = Item::new("portal_env");
+    /// Shares its namespace with `positions`, which holds both layouts until the migration ends
+    pub const LS_DATA: Map<(&str, u32), LiquidStakingData> = Map::new("ls_data");
+    /// Set by `migrate` while positions are left to move, to after the last one moved if any
+    pub const MIGRATION_CURSOR: Item<Option<(String, u32)>> = Item::new("v0_1_migration_cursor");
+}
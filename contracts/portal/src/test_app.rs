@@ -0,0 +1,999 @@
+//! Offline test app for the portal.
+//!
+//! Plain cw-multi-test can neither execute the `MsgInstantiateContract2` stargate
+//! messages the portal emits nor answer the Finschia custom query used to fetch
+//! code data hashes. This module wires stand-ins for both into an `App`, so the
+//! whole delegate -> withdraw reward -> undelegate flow can be driven in tests.
+#![cfg(test)]
+
+use std::fmt::Debug;
+use std::ops::Deref;
+
+use anyhow::{bail, Result as AnyResult};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::testing::{mock_env, MockApi, MockStorage};
+use cosmwasm_std::{
+    coin, instantiate2_address, Addr, Api, Binary, BlockInfo, CanonicalAddr, Coin, CustomQuery,
+    Decimal, Deps, DepsMut, Empty, Env, FullDelegation, MessageInfo, Order, Querier, Reply,
+    Response, StakingMsg, StakingQuery, StdResult, Storage, Timestamp, Uint128, Validator, WasmMsg,
+};
+use cw_multi_test::{
+    AddressGenerator, App, AppBuilder, AppResponse, BankKeeper, Contract, ContractWrapper,
+    CosmosRouter, DistributionKeeper, Executor, GovFailingModule, IbcFailingModule, Module,
+    StakeKeeper, Staking, StakingInfo, StakingSudo, Stargate, StargateMsg, StargateQuery, SudoMsg,
+    WasmKeeper,
+};
+use cw_storage_plus::Map;
+use delegator::querier::{
+    QueryDelegatorUnbondingDelegationsRequest, QueryDelegatorUnbondingDelegationsResponse,
+    UnbondingDelegation, UnbondingDelegationEntry,
+};
+use prost::Message;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+
+use crate::cosmos_msg::MsgInstantiateContract2;
+use crate::msg::{
+    DataResponse, ExchangeRateResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg,
+};
+use crate::position_id::PositionId;
+use crate::querier::{
+    CodeInfoResponse, CustomQuery as PortalQuery, Description, Duration, Params, QueryCodeRequest,
+    QueryCodeResponse, QueryParamsResponse, QueryValidatorRequest, QueryValidatorResponse,
+    ValidatorInfo, BOND_STATUS_BONDED,
+};
+
+/// `cosmos.staking.v1beta1.BondStatus` of a validator leaving the active set
+const BOND_STATUS_UNBONDING: i32 = 2;
+
+pub const DENOM: &str = "cony";
+pub const OTHER_DENOM: &str = "uatom";
+pub const VALIDATOR: &str = "linkvaloper1validator";
+pub const OTHER_VALIDATOR: &str = "linkvaloper1othervalidator";
+pub const MONIKER: &str = "Cony";
+pub const OTHER_MONIKER: &str = "Brown";
+/// Known to the staking module, but jailed out of the active set
+pub const JAILED_VALIDATOR: &str = "linkvaloper1jailedvalidator";
+pub const JAILED_MONIKER: &str = "Sally";
+/// Bonded, but reported without a description
+pub const ANONYMOUS_VALIDATOR: &str = "linkvaloper1anonymousvalidator";
+pub const UNBONDING_TIME: u64 = 60 * 60 * 24 * 21;
+pub const INITIAL_BALANCE: u128 = 1_000_000_000;
+
+pub type PortalApp = App<
+    BankKeeper,
+    MockApi,
+    MockStorage,
+    ChainQuerier,
+    WasmKeeper<Empty, PortalQuery>,
+    UnbondingStakeKeeper,
+    DistributionKeeper,
+    IbcFailingModule,
+    GovFailingModule,
+    InstantiateStargate,
+>;
+
+/// Data hash the test chain reports for a stored code.
+/// Both the custom querier and the address generator derive it from the code id,
+/// so the portal predicts exactly the address the wasm keeper assigns.
+pub fn code_checksum(code_id: u64) -> Vec<u8> {
+    Sha256::digest(code_id.to_be_bytes()).to_vec()
+}
+
+/// Serves the gRPC path custom queries issued through `crate::querier`.
+pub struct ChainQuerier;
+
+impl Module for ChainQuerier {
+    type ExecT = Empty;
+    type QueryT = PortalQuery;
+    type SudoT = Empty;
+
+    fn execute<ExecC, QueryC>(
+        &self,
+        _api: &dyn Api,
+        _storage: &mut dyn Storage,
+        _router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        _block: &BlockInfo,
+        _sender: Addr,
+        msg: Self::ExecT,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        bail!("Unexpected custom exec msg {:?}", msg)
+    }
+
+    fn query(
+        &self,
+        _api: &dyn Api,
+        storage: &dyn Storage,
+        _querier: &dyn Querier,
+        _block: &BlockInfo,
+        request: Self::QueryT,
+    ) -> AnyResult<Binary> {
+        match request.path.as_str() {
+            "/cosmwasm.wasm.v1.Query/Code" => {
+                let req = QueryCodeRequest::decode(request.data.as_slice())?;
+                let res = QueryCodeResponse {
+                    code_info: Some(CodeInfoResponse {
+                        data_hash: code_checksum(req.code_id),
+                    }),
+                };
+                Ok(res.encode_to_vec().into())
+            }
+            "/cosmos.staking.v1beta1.Query/Params" => {
+                let res = QueryParamsResponse {
+                    params: Some(Params {
+                        unbonding_time: Some(Duration {
+                            seconds: UNBONDING_TIME as i64,
+                            nanos: 0,
+                        }),
+                    }),
+                };
+                Ok(res.encode_to_vec().into())
+            }
+            "/cosmos.staking.v1beta1.Query/Validator" => {
+                let req = QueryValidatorRequest::decode(request.data.as_slice())?;
+                let (moniker, jailed, status) = match req.validator_addr.as_str() {
+                    VALIDATOR => (Some(MONIKER), false, BOND_STATUS_BONDED),
+                    OTHER_VALIDATOR => (Some(OTHER_MONIKER), false, BOND_STATUS_BONDED),
+                    JAILED_VALIDATOR => (Some(JAILED_MONIKER), true, BOND_STATUS_UNBONDING),
+                    ANONYMOUS_VALIDATOR => (None, false, BOND_STATUS_BONDED),
+                    validator => bail!("Unknown validator {}", validator),
+                };
+                let res = QueryValidatorResponse {
+                    validator: Some(ValidatorInfo {
+                        jailed,
+                        status,
+                        description: moniker.map(|moniker| Description {
+                            moniker: moniker.to_string(),
+                        }),
+                    }),
+                };
+                Ok(res.encode_to_vec().into())
+            }
+            "/cosmos.staking.v1beta1.Query/DelegatorUnbondingDelegations" => {
+                let req =
+                    QueryDelegatorUnbondingDelegationsRequest::decode(request.data.as_slice())?;
+                let unbonding_responses = UNBONDING_ENTRIES
+                    .prefix(&req.delegator_addr)
+                    .range(storage, None, None, Order::Ascending)
+                    .map(|item| {
+                        let (validator, entries) = item?;
+                        Ok(UnbondingDelegation {
+                            delegator_address: req.delegator_addr.clone(),
+                            validator_address: validator,
+                            entries: entries
+                                .into_iter()
+                                .map(|entry| UnbondingDelegationEntry {
+                                    creation_height: entry.creation_height as i64,
+                                    initial_balance: entry.initial_balance.to_string(),
+                                    balance: entry.balance.to_string(),
+                                })
+                                .collect(),
+                        })
+                    })
+                    .collect::<StdResult<Vec<_>>>()?;
+                let res = QueryDelegatorUnbondingDelegationsResponse {
+                    unbonding_responses,
+                };
+                Ok(res.encode_to_vec().into())
+            }
+            path => bail!("Unexpected custom query path {}", path),
+        }
+    }
+
+    fn sudo<ExecC, QueryC>(
+        &self,
+        _api: &dyn Api,
+        _storage: &mut dyn Storage,
+        _router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        _block: &BlockInfo,
+        msg: Self::SudoT,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        bail!("Unexpected custom sudo msg {:?}", msg)
+    }
+}
+
+/// Unbonding entry of the staking module, which `StakeKeeper` keeps to itself
+#[cw_serde]
+struct UnbondingEntry {
+    creation_height: u64,
+    completion_time: Timestamp,
+    initial_balance: Uint128,
+    balance: Uint128,
+}
+
+/// Entries by delegator and validator, undelegations of the same block share one like on chain
+const UNBONDING_ENTRIES: Map<(&str, &str), Vec<UnbondingEntry>> =
+    Map::new("test_unbonding_entries");
+
+/// `StakeKeeper` that also tracks the unbonding entries, slashing and releasing them along
+/// with its queue, so `ChainQuerier` can report them.
+pub struct UnbondingStakeKeeper(StakeKeeper);
+
+impl UnbondingStakeKeeper {
+    pub fn new() -> Self {
+        UnbondingStakeKeeper(StakeKeeper::new())
+    }
+}
+
+impl Default for UnbondingStakeKeeper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for UnbondingStakeKeeper {
+    type Target = StakeKeeper;
+
+    fn deref(&self) -> &StakeKeeper {
+        &self.0
+    }
+}
+
+impl Module for UnbondingStakeKeeper {
+    type ExecT = StakingMsg;
+    type QueryT = StakingQuery;
+    type SudoT = StakingSudo;
+
+    fn execute<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        msg: Self::ExecT,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        let res = self
+            .0
+            .execute(api, storage, router, block, sender.clone(), msg.clone())?;
+        if let StakingMsg::Undelegate { validator, amount } = msg {
+            let key = (sender.as_str(), validator.as_str());
+            let mut entries = UNBONDING_ENTRIES
+                .may_load(storage, key)?
+                .unwrap_or_default();
+            match entries
+                .iter_mut()
+                .find(|entry| entry.creation_height == block.height)
+            {
+                Some(entry) => {
+                    entry.initial_balance += amount.amount;
+                    entry.balance += amount.amount;
+                }
+                None => entries.push(UnbondingEntry {
+                    creation_height: block.height,
+                    completion_time: block.time.plus_seconds(UNBONDING_TIME),
+                    initial_balance: amount.amount,
+                    balance: amount.amount,
+                }),
+            }
+            UNBONDING_ENTRIES.save(storage, key, &entries)?;
+        }
+        Ok(res)
+    }
+
+    fn query(
+        &self,
+        api: &dyn Api,
+        storage: &dyn Storage,
+        querier: &dyn Querier,
+        block: &BlockInfo,
+        request: Self::QueryT,
+    ) -> AnyResult<Binary> {
+        self.0.query(api, storage, querier, block, request)
+    }
+
+    fn sudo<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        msg: Self::SudoT,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        let keys = UNBONDING_ENTRIES
+            .keys(storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        for (delegator, validator) in keys {
+            let key = (delegator.as_str(), validator.as_str());
+            let mut entries = UNBONDING_ENTRIES.load(storage, key)?;
+            match &msg {
+                StakingSudo::Slash {
+                    validator: slashed,
+                    percentage,
+                } if *slashed == validator => {
+                    for entry in entries.iter_mut() {
+                        entry.balance = entry.balance * (Decimal::one() - *percentage);
+                    }
+                }
+                StakingSudo::ProcessQueue {} => {
+                    entries.retain(|entry| entry.completion_time > block.time);
+                }
+                _ => continue,
+            }
+            if entries.is_empty() {
+                UNBONDING_ENTRIES.remove(storage, key);
+            } else {
+                UNBONDING_ENTRIES.save(storage, key, &entries)?;
+            }
+        }
+        self.0.sudo(api, storage, router, block, msg)
+    }
+}
+
+impl Staking for UnbondingStakeKeeper {}
+
+/// Executes `/cosmwasm.wasm.v1.MsgInstantiateContract2` by routing it as a `WasmMsg::Instantiate2`.
+pub struct InstantiateStargate;
+
+impl Module for InstantiateStargate {
+    type ExecT = StargateMsg;
+    type QueryT = StargateQuery;
+    type SudoT = Empty;
+
+    fn execute<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        msg: Self::ExecT,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        match msg.type_url.as_str() {
+            "/cosmwasm.wasm.v1.MsgInstantiateContract2" => {
+                let init = MsgInstantiateContract2::decode(msg.value.as_slice())?;
+                if init.sender != sender.as_str() {
+                    bail!("Signer {} does not match sender {}", init.sender, sender)
+                }
+                let funds = init
+                    .funds
+                    .iter()
+                    .map(|c| {
+                        Ok(Coin {
+                            denom: c.denom.clone(),
+                            amount: c.amount.parse::<Uint128>()?,
+                        })
+                    })
+                    .collect::<AnyResult<Vec<_>>>()?;
+                let wasm_msg = WasmMsg::Instantiate2 {
+                    admin: Some(init.admin).filter(|admin| !admin.is_empty()),
+                    code_id: init.code_id,
+                    label: init.label,
+                    msg: init.msg.into(),
+                    funds,
+                    salt: init.salt.into(),
+                };
+                router.execute(api, storage, block, sender, wasm_msg.into())
+            }
+            type_url => bail!("Unexpected stargate msg {}", type_url),
+        }
+    }
+
+    fn query(
+        &self,
+        _api: &dyn Api,
+        _storage: &dyn Storage,
+        _querier: &dyn Querier,
+        _block: &BlockInfo,
+        request: Self::QueryT,
+    ) -> AnyResult<Binary> {
+        bail!("Unexpected stargate query {}", request.path)
+    }
+
+    fn sudo<ExecC, QueryC>(
+        &self,
+        _api: &dyn Api,
+        _storage: &mut dyn Storage,
+        _router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        _block: &BlockInfo,
+        msg: Self::SudoT,
+    ) -> AnyResult<AppResponse>
+    where
+        ExecC: Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        bail!("Unexpected stargate sudo msg {:?}", msg)
+    }
+}
+
+impl Stargate for InstantiateStargate {}
+
+/// Derives instantiate2 addresses from `code_checksum` like the chain does.
+struct PredictableAddressGenerator;
+
+impl AddressGenerator for PredictableAddressGenerator {
+    fn contract_address(
+        &self,
+        api: &dyn Api,
+        _storage: &mut dyn Storage,
+        _code_id: u64,
+        instance_id: u64,
+    ) -> AnyResult<Addr> {
+        let canonical = Sha256::digest(format!("contract{}", instance_id)).to_vec();
+        Ok(api.addr_humanize(&canonical.into())?)
+    }
+
+    fn predictable_contract_address(
+        &self,
+        api: &dyn Api,
+        _storage: &mut dyn Storage,
+        code_id: u64,
+        _instance_id: u64,
+        _checksum: &[u8],
+        creator: &CanonicalAddr,
+        salt: &[u8],
+    ) -> AnyResult<Addr> {
+        let canonical = instantiate2_address(&code_checksum(code_id), creator, salt)?;
+        Ok(api.addr_humanize(&canonical)?)
+    }
+}
+
+// The contracts below are written against `Deps<Empty>`, while the test app
+// carries the portal custom query type, so every entry point is narrowed here.
+
+fn portal_contract() -> Box<dyn Contract<Empty, PortalQuery>> {
+    let contract = ContractWrapper::new(
+        |deps: DepsMut<PortalQuery>, env: Env, info: MessageInfo, msg: ExecuteMsg| {
+            crate::contract::execute(deps.into_empty(), env, info, msg)
+        },
+        |deps: DepsMut<PortalQuery>, env: Env, info: MessageInfo, msg: InstantiateMsg| {
+            crate::contract::instantiate(deps.into_empty(), env, info, msg)
+        },
+        |deps: Deps<PortalQuery>, env: Env, msg: QueryMsg| {
+            crate::contract::query(deps.into_empty(), env, msg)
+        },
+    )
+    .with_reply(|deps: DepsMut<PortalQuery>, env: Env, msg: Reply| {
+        crate::contract::reply(deps.into_empty(), env, msg)
+    })
+    .with_migrate(|deps: DepsMut<PortalQuery>, env: Env, msg: MigrateMsg| {
+        crate::contract::migrate(deps.into_empty(), env, msg)
+    });
+    Box::new(contract)
+}
+
+pub fn cw20_contract() -> Box<dyn Contract<Empty, PortalQuery>> {
+    let contract = ContractWrapper::new(
+        |deps: DepsMut<PortalQuery>,
+         env: Env,
+         info: MessageInfo,
+         msg: cw20_base::msg::ExecuteMsg| {
+            cw20_base::contract::execute(deps.into_empty(), env, info, msg)
+        },
+        |deps: DepsMut<PortalQuery>,
+         env: Env,
+         info: MessageInfo,
+         msg: cw20_base::msg::InstantiateMsg| {
+            cw20_base::contract::instantiate(deps.into_empty(), env, info, msg)
+        },
+        |deps: Deps<PortalQuery>, env: Env, msg: cw20_base::msg::QueryMsg| {
+            cw20_base::contract::query(deps.into_empty(), env, msg)
+        },
+    )
+    .with_migrate(
+        |deps: DepsMut<PortalQuery>, env: Env, msg: cw20_base::msg::MigrateMsg| {
+            cw20_base::contract::migrate(deps.into_empty(), env, msg)
+        },
+    );
+    Box::new(contract)
+}
+
+fn cw721_contract() -> Box<dyn Contract<Empty, PortalQuery>> {
+    let contract = ContractWrapper::new(
+        |deps: DepsMut<PortalQuery>,
+         env: Env,
+         info: MessageInfo,
+         msg: cw721_base::ExecuteMsg<cw721_base::Extension, cw721_base::ExtensionMsg>| {
+            cw721_base::entry::execute(deps.into_empty(), env, info, msg)
+        },
+        |deps: DepsMut<PortalQuery>,
+         env: Env,
+         info: MessageInfo,
+         msg: cw721_base::InstantiateMsg| {
+            cw721_base::entry::instantiate(deps.into_empty(), env, info, msg)
+        },
+        |deps: Deps<PortalQuery>, env: Env, msg: cw721_base::QueryMsg<Empty>| {
+            cw721_base::entry::query(deps.into_empty(), env, msg)
+        },
+    )
+    .with_migrate(|deps: DepsMut<PortalQuery>, env: Env, msg: Empty| {
+        cw721_base::entry::migrate(deps.into_empty(), env, msg)
+    });
+    Box::new(contract)
+}
+
+/// The cw721 of 0.1.x, which minted records without metadata. Its migrate entry point only
+/// exists so tests can move a cw721 back to this code.
+pub fn legacy_cw721_contract() -> Box<dyn Contract<Empty, PortalQuery>> {
+    type Legacy<'a> = cw721_base::Cw721Contract<'a, Option<Empty>, Empty, Empty, Empty>;
+    let contract = ContractWrapper::new(
+        |deps: DepsMut<PortalQuery>,
+         env: Env,
+         info: MessageInfo,
+         msg: cw721_base::ExecuteMsg<Option<Empty>, Empty>| {
+            Legacy::default().execute(deps.into_empty(), env, info, msg)
+        },
+        |deps: DepsMut<PortalQuery>,
+         env: Env,
+         info: MessageInfo,
+         msg: cw721_base::InstantiateMsg| {
+            Legacy::default().instantiate(deps.into_empty(), env, info, msg)
+        },
+        |deps: Deps<PortalQuery>, env: Env, msg: cw721_base::QueryMsg<Empty>| {
+            Legacy::default().query(deps.into_empty(), env, msg)
+        },
+    )
+    .with_migrate(
+        |_deps: DepsMut<PortalQuery>, _env: Env, _msg: Empty| -> StdResult<Response> {
+            Ok(Response::new())
+        },
+    );
+    Box::new(contract)
+}
+
+pub fn delegator_contract() -> Box<dyn Contract<Empty, PortalQuery>> {
+    let contract = ContractWrapper::new(
+        |deps: DepsMut<PortalQuery>,
+         env: Env,
+         info: MessageInfo,
+         msg: delegator::msg::ExecuteMsg| {
+            delegator::contract::execute(deps.into_empty(), env, info, msg)
+        },
+        |deps: DepsMut<PortalQuery>,
+         env: Env,
+         info: MessageInfo,
+         msg: delegator::msg::InstantiateMsg| {
+            delegator::contract::instantiate(deps.into_empty(), env, info, msg)
+        },
+        |deps: Deps<PortalQuery>, env: Env, msg: delegator::msg::QueryMsg| {
+            delegator::contract::query(deps.into_empty(), env, msg)
+        },
+    )
+    .with_migrate(
+        |deps: DepsMut<PortalQuery>, env: Env, msg: delegator::msg::MigrateMsg| {
+            delegator::contract::migrate(deps.into_empty(), env, msg)
+        },
+    );
+    Box::new(contract)
+}
+
+pub struct CodeIds {
+    pub portal: u64,
+    pub cw20: u64,
+    pub cw721: u64,
+    pub delegator: u64,
+}
+
+/// A portal deployed on the test app, with a funded user and a bonded validator.
+pub struct Suite {
+    pub app: PortalApp,
+    pub code_ids: CodeIds,
+    pub owner: Addr,
+    pub user: Addr,
+    pub portal: Addr,
+    pub cw721: Addr,
+}
+
+impl Default for Suite {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Suite {
+    pub fn new() -> Self {
+        Self::init(None)
+    }
+
+    pub fn init(min_delegation: Option<Uint128>) -> Self {
+        let api = MockApi::default();
+        let owner = api.addr_make("owner");
+        let user = api.addr_make("user");
+
+        let mut app = AppBuilder::new_custom()
+            .with_api(api)
+            .with_custom(ChainQuerier)
+            .with_staking(UnbondingStakeKeeper::new())
+            .with_stargate(InstantiateStargate)
+            .with_wasm(WasmKeeper::new().with_address_generator(PredictableAddressGenerator))
+            .build(|router, api, storage| {
+                router
+                    .staking
+                    .setup(
+                        storage,
+                        StakingInfo {
+                            bonded_denom: DENOM.to_string(),
+                            unbonding_time: UNBONDING_TIME,
+                            apr: Decimal::percent(10),
+                        },
+                    )
+                    .unwrap();
+                router
+                    .staking
+                    .add_validator(
+                        api,
+                        storage,
+                        &mock_env().block,
+                        Validator {
+                            address: VALIDATOR.to_string(),
+                            commission: Decimal::zero(),
+                            max_commission: Decimal::one(),
+                            max_change_rate: Decimal::one(),
+                        },
+                    )
+                    .unwrap();
+                router
+                    .staking
+                    .add_validator(
+                        api,
+                        storage,
+                        &mock_env().block,
+                        Validator {
+                            address: OTHER_VALIDATOR.to_string(),
+                            commission: Decimal::percent(5),
+                            max_commission: Decimal::one(),
+                            max_change_rate: Decimal::one(),
+                        },
+                    )
+                    .unwrap();
+                router
+                    .staking
+                    .add_validator(
+                        api,
+                        storage,
+                        &mock_env().block,
+                        Validator {
+                            address: JAILED_VALIDATOR.to_string(),
+                            commission: Decimal::zero(),
+                            max_commission: Decimal::one(),
+                            max_change_rate: Decimal::one(),
+                        },
+                    )
+                    .unwrap();
+                router
+                    .staking
+                    .add_validator(
+                        api,
+                        storage,
+                        &mock_env().block,
+                        Validator {
+                            address: ANONYMOUS_VALIDATOR.to_string(),
+                            commission: Decimal::zero(),
+                            max_commission: Decimal::one(),
+                            max_change_rate: Decimal::one(),
+                        },
+                    )
+                    .unwrap();
+                router
+                    .bank
+                    .init_balance(
+                        storage,
+                        &user,
+                        vec![
+                            coin(INITIAL_BALANCE, DENOM),
+                            coin(INITIAL_BALANCE, OTHER_DENOM),
+                        ],
+                    )
+                    .unwrap();
+            });
+
+        let code_ids = CodeIds {
+            portal: app.store_code(portal_contract()),
+            cw20: app.store_code(cw20_contract()),
+            cw721: app.store_code(cw721_contract()),
+            delegator: app.store_code(delegator_contract()),
+        };
+
+        let portal = app
+            .instantiate_contract(
+                code_ids.portal,
+                owner.clone(),
+                &InstantiateMsg {
+                    cw20_code_id: code_ids.cw20,
+                    cw721_code_id: code_ids.cw721,
+                    delegator_code_id: code_ids.delegator,
+                    min_delegation,
+                },
+                &[],
+                "portal",
+                Some(owner.to_string()),
+            )
+            .unwrap();
+
+        let env: crate::msg::EnvResponse = app
+            .wrap()
+            .query_wasm_smart(&portal, &QueryMsg::Env {})
+            .unwrap();
+
+        Suite {
+            app,
+            code_ids,
+            owner,
+            user,
+            portal,
+            cw721: Addr::unchecked(env.cw721_address),
+        }
+    }
+
+    pub fn delegate_and_tokenize(
+        &mut self,
+        sender: &Addr,
+        validator: &str,
+        funds: &[Coin],
+    ) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            sender.clone(),
+            self.portal.clone(),
+            &ExecuteMsg::DelegateAndTokenize {
+                validator: validator.to_string(),
+            },
+            funds,
+        )
+    }
+
+    pub fn withdraw_all_reward(&mut self, sender: &Addr) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            sender.clone(),
+            self.portal.clone(),
+            &ExecuteMsg::WithdrawAllReward {
+                start_after: None,
+                limit: None,
+            },
+            &[],
+        )
+    }
+
+    pub fn update_validator_allow_list(
+        &mut self,
+        sender: &Addr,
+        enabled: bool,
+        add: &[&str],
+        remove: &[&str],
+    ) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            sender.clone(),
+            self.portal.clone(),
+            &ExecuteMsg::UpdateValidatorAllowList {
+                enabled,
+                add: add.iter().map(|v| v.to_string()).collect(),
+                remove: remove.iter().map(|v| v.to_string()).collect(),
+            },
+            &[],
+        )
+    }
+
+    pub fn withdraw_rewards(
+        &mut self,
+        sender: &Addr,
+        ids: &[PositionId],
+    ) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            sender.clone(),
+            self.portal.clone(),
+            &ExecuteMsg::WithdrawRewards { ids: ids.to_vec() },
+            &[],
+        )
+    }
+
+    pub fn transfer_nft(
+        &mut self,
+        sender: &Addr,
+        recipient: &Addr,
+        id: &PositionId,
+    ) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            sender.clone(),
+            self.cw721.clone(),
+            &cw721_base::ExecuteMsg::<cw721_base::Extension, cw721_base::ExtensionMsg>::TransferNft {
+                recipient: recipient.to_string(),
+                token_id: id.to_string(),
+            },
+            &[],
+        )
+    }
+
+    /// Approves the portal to burn `amount` of the position token, then undelegates it.
+    pub fn undelegate(
+        &mut self,
+        sender: &Addr,
+        id: &PositionId,
+        amount: u128,
+    ) -> AnyResult<AppResponse> {
+        let data = self.data(id);
+        self.app.execute_contract(
+            sender.clone(),
+            Addr::unchecked(data.token_address),
+            &cw20_base::msg::ExecuteMsg::IncreaseAllowance {
+                spender: self.portal.to_string(),
+                amount: Uint128::new(amount),
+                expires: None,
+            },
+            &[],
+        )?;
+        self.app.execute_contract(
+            sender.clone(),
+            self.portal.clone(),
+            &ExecuteMsg::Undelegate {
+                id: id.clone(),
+                amount: Uint128::new(amount),
+            },
+            &[],
+        )
+    }
+
+    pub fn claim(&mut self, sender: &Addr) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            sender.clone(),
+            self.portal.clone(),
+            &ExecuteMsg::Claim {},
+            &[],
+        )
+    }
+
+    pub fn transfer_tokens(
+        &mut self,
+        sender: &Addr,
+        id: &PositionId,
+        recipient: &Addr,
+        amount: u128,
+    ) -> AnyResult<AppResponse> {
+        let data = self.data(id);
+        self.app.execute_contract(
+            sender.clone(),
+            Addr::unchecked(data.token_address),
+            &cw20_base::msg::ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount: Uint128::new(amount),
+            },
+            &[],
+        )
+    }
+
+    /// Claims the sender's share of the rewards withdrawn to the position token.
+    pub fn claim_rewards(&mut self, sender: &Addr, id: &PositionId) -> AnyResult<AppResponse> {
+        let data = self.data(id);
+        self.app.execute_contract(
+            sender.clone(),
+            Addr::unchecked(data.token_address),
+            &cw20_base::msg::ExecuteMsg::ClaimRewards { recipient: None },
+            &[],
+        )
+    }
+
+    pub fn data(&self, id: &PositionId) -> DataResponse {
+        self.app
+            .wrap()
+            .query_wasm_smart(&self.portal, &QueryMsg::Data { id: id.clone() })
+            .unwrap()
+    }
+
+    pub fn query_delegator<T: DeserializeOwned>(
+        &self,
+        id: &PositionId,
+        msg: &delegator::msg::QueryMsg,
+    ) -> T {
+        self.app
+            .wrap()
+            .query_wasm_smart(self.data(id).delegator_address, msg)
+            .unwrap()
+    }
+
+    pub fn exchange_rate(&self, id: &PositionId) -> ExchangeRateResponse {
+        self.app
+            .wrap()
+            .query_wasm_smart(&self.portal, &QueryMsg::ExchangeRate { id: id.clone() })
+            .unwrap()
+    }
+
+    pub fn cw20_balance(&self, token: &str, address: &Addr) -> Uint128 {
+        let res: cw20::BalanceResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(
+                token,
+                &cw20_base::msg::QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap();
+        res.balance
+    }
+
+    pub fn nft_owner(&self, id: &PositionId) -> String {
+        let res: cw721::OwnerOfResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(
+                &self.cw721,
+                &cw721_base::QueryMsg::<Empty>::OwnerOf {
+                    token_id: id.to_string(),
+                    include_expired: None,
+                },
+            )
+            .unwrap();
+        res.owner
+    }
+
+    pub fn nft_info(&self, id: &PositionId) -> cw721::NftInfoResponse<cw721_base::Extension> {
+        self.app
+            .wrap()
+            .query_wasm_smart(
+                &self.cw721,
+                &cw721_base::QueryMsg::<Empty>::NftInfo {
+                    token_id: id.to_string(),
+                },
+            )
+            .unwrap()
+    }
+
+    pub fn delegation(&self, delegator: &str) -> Option<FullDelegation> {
+        self.app
+            .wrap()
+            .query_delegation(delegator, VALIDATOR)
+            .unwrap()
+    }
+
+    pub fn balance(&self, address: &Addr) -> Uint128 {
+        self.app
+            .wrap()
+            .query_balance(address, DENOM)
+            .unwrap()
+            .amount
+    }
+
+    /// Releases matured unbondings on the staking module
+    pub fn process_unbondings(&mut self) {
+        self.app
+            .sudo(SudoMsg::Staking(StakingSudo::ProcessQueue {}))
+            .unwrap();
+    }
+
+    /// Slashes `percent` of everything bonded to the validator
+    pub fn slash(&mut self, validator: &str, percent: u64) {
+        self.app
+            .sudo(SudoMsg::Staking(StakingSudo::Slash {
+                validator: validator.to_string(),
+                percentage: Decimal::percent(percent),
+            }))
+            .unwrap();
+    }
+
+    /// Runs `f` on the storage of `contract`, to set up state this code no longer writes
+    pub fn with_contract_storage<T>(
+        &mut self,
+        contract: &Addr,
+        f: impl FnOnce(&mut dyn Storage) -> T,
+    ) -> T {
+        self.app.init_modules(|router, _api, storage| {
+            f(&mut *router.wasm.contract_storage_mut(storage, contract))
+        })
+    }
+
+    pub fn advance_time(&mut self, seconds: u64) {
+        self.app.update_block(|block| {
+            block.time = block.time.plus_seconds(seconds);
+            block.height += seconds / 5;
+        });
+    }
+}